@@ -0,0 +1,603 @@
+use crate::config::GameConfig;
+use crate::strategy::Strategy;
+use crate::{take, BonusCard, DamageCard, EventCard, GameState, LogEntry, RoleName};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// Marks an event in play whose damage card has not been looked at yet.
+const NO_DAMAGE: u8 = u8::MAX;
+
+/// Everything about a position that can affect the rest of the game, with
+/// cards replaced by their index in the `Codec` tables. Decks are stored as a
+/// count per distinct card: what is left of a shuffled deck is equally likely
+/// to be in any order, so only its composition matters.
+#[derive(Clone)]
+struct Position {
+    year_number: u8,
+    infra_damage: u8,
+    /// Facilities, damaged facilities, resources and acted, in `Codec::roles` order.
+    roles: Vec<[u8; 4]>,
+    /// Bonus cards held, as (role, card) pairs in sorted order.
+    hands: Vec<(u8, u8)>,
+    event_cards_in_play: Vec<(u8, u8)>,
+    event_deck: Vec<u8>,
+    bonus_deck: Vec<u8>,
+    damage_deck: Vec<u8>,
+    /// Damage cards drawn for events that were dealt with. Nobody looked at
+    /// them, so they are still counted in `damage_deck`: each card left in
+    /// the real deck is as likely to be any of those counted.
+    burned: u8,
+}
+
+/// Translates between game states and compact keys.
+struct Codec {
+    template: GameState,
+    roles: Vec<RoleName>,
+    events: Vec<EventCard>,
+    bonuses: Vec<BonusCard>,
+    damages: Vec<DamageCard>,
+}
+
+fn distinct<T: Ord + Clone>(cards: &[T]) -> Vec<T> {
+    let mut cards = cards.to_vec();
+    cards.sort();
+    cards.dedup();
+    cards
+}
+
+fn index_of<T: PartialEq>(table: &[T], card: &T) -> u8 {
+    table
+        .iter()
+        .position(|c| c == card)
+        .expect("card missing from the deck definition") as u8
+}
+
+fn counts<T: PartialEq>(table: &[T], deck: &[T]) -> Vec<u8> {
+    table
+        .iter()
+        .map(|card| deck.iter().filter(|c| *c == card).count() as u8)
+        .collect()
+}
+
+fn expand<T: Clone>(table: &[T], counts: &[u8]) -> Vec<T> {
+    let mut deck = Vec::new();
+    for (card, n) in table.iter().zip(counts) {
+        for _ in 0..*n {
+            deck.push(card.clone());
+        }
+    }
+    deck
+}
+
+/// An event card as it sits in the deck, before a damage card is attached.
+fn undealt(card: &EventCard) -> (EventCard, Option<DamageCard>) {
+    match card {
+        EventCard::Problem(id, role_costs, damage_rule, damage) => (
+            EventCard::Problem(id.clone(), role_costs.clone(), damage_rule.clone(), None),
+            damage.clone(),
+        ),
+        other => (other.clone(), None),
+    }
+}
+
+impl Codec {
+    fn new(config: &GameConfig) -> Codec {
+        let mut template = GameState::from_config(config);
+        template.verbose = false;
+        template.event_deck.clear();
+        template.bonus_deck.clear();
+        template.damage_deck.clear();
+        let mut roles: Vec<RoleName> = template.roles.keys().cloned().collect();
+        roles.sort();
+        let events: Vec<EventCard> = config.event_deck.iter().map(|c| undealt(c).0).collect();
+        Codec {
+            roles,
+            events: distinct(&events),
+            bonuses: distinct(&config.bonus_deck),
+            damages: distinct(&config.damage_deck),
+            template,
+        }
+    }
+
+    fn encode(&self, gs: &GameState) -> Position {
+        Position {
+            year_number: gs.year_number as u8,
+            infra_damage: gs.infra_damage as u8,
+            roles: self
+                .roles
+                .iter()
+                .map(|name| {
+                    let role = &gs.roles[name];
+                    [
+                        role.facilities as u8,
+                        role.facilites_damaged as u8,
+                        role.resources as u8,
+                        role.acted as u8,
+                    ]
+                })
+                .collect(),
+            hands: {
+                let mut hands: Vec<(u8, u8)> = Vec::new();
+                for (i, name) in self.roles.iter().enumerate() {
+                    for card in &gs.roles[name].hand {
+                        hands.push((i as u8, index_of(&self.bonuses, card)));
+                    }
+                }
+                hands.sort();
+                hands
+            },
+            event_cards_in_play: gs
+                .event_cards_in_play
+                .iter()
+                .map(|card| {
+                    let (card, damage) = undealt(card);
+                    let damage = damage.map_or(NO_DAMAGE, |d| index_of(&self.damages, &d));
+                    (index_of(&self.events, &card), damage)
+                })
+                .collect(),
+            event_deck: counts(&self.events, &gs.event_deck),
+            bonus_deck: counts(&self.bonuses, &gs.bonus_deck),
+            damage_deck: counts(&self.damages, &gs.damage_deck),
+            burned: 0,
+        }
+    }
+
+    /// Packs a position into bytes, the form held in a `Distribution`.
+    fn pack(&self, key: &Position) -> Vec<u8> {
+        let mut bytes = vec![
+            key.year_number,
+            key.infra_damage,
+            key.burned,
+            key.hands.len() as u8,
+            key.event_cards_in_play.len() as u8,
+        ];
+        for role in &key.roles {
+            bytes.extend_from_slice(role);
+        }
+        for (role, card) in &key.hands {
+            bytes.push(*role);
+            bytes.push(*card);
+        }
+        for (card, damage) in &key.event_cards_in_play {
+            bytes.push(*card);
+            bytes.push(*damage);
+        }
+        bytes.extend_from_slice(&key.event_deck);
+        bytes.extend_from_slice(&key.bonus_deck);
+        bytes.extend_from_slice(&key.damage_deck);
+        bytes
+    }
+
+    fn unpack(&self, bytes: &[u8]) -> Position {
+        let (header, mut rest) = bytes.split_at(5);
+        let mut take = |n: usize| {
+            let (taken, left) = rest.split_at(n);
+            rest = left;
+            taken
+        };
+        let roles = take(4 * self.roles.len())
+            .chunks(4)
+            .map(|role| [role[0], role[1], role[2], role[3]])
+            .collect();
+        let hands = take(2 * header[3] as usize)
+            .chunks(2)
+            .map(|pair| (pair[0], pair[1]))
+            .collect();
+        let event_cards_in_play = take(2 * header[4] as usize)
+            .chunks(2)
+            .map(|pair| (pair[0], pair[1]))
+            .collect();
+        Position {
+            year_number: header[0],
+            infra_damage: header[1],
+            burned: header[2],
+            roles,
+            hands,
+            event_cards_in_play,
+            event_deck: take(self.events.len()).to_vec(),
+            bonus_deck: take(self.bonuses.len()).to_vec(),
+            damage_deck: take(self.damages.len()).to_vec(),
+        }
+    }
+
+    fn decode(&self, key: &Position) -> GameState {
+        let mut gs = self.template.clone();
+        gs.year_number = key.year_number as usize;
+        gs.infra_damage = key.infra_damage as usize;
+        for (name, [facilities, damaged, resources, acted]) in self.roles.iter().zip(&key.roles) {
+            if let Some(role) = gs.roles.get_mut(name) {
+                role.facilities = *facilities as usize;
+                role.facilites_damaged = *damaged as usize;
+                role.resources = *resources as usize;
+                role.acted = *acted != 0;
+                role.hand.clear();
+            }
+        }
+        for (role, card) in &key.hands {
+            let name = &self.roles[*role as usize];
+            if let Some(role) = gs.roles.get_mut(name) {
+                role.hand.push(self.bonuses[*card as usize].clone());
+            }
+        }
+        gs.event_cards_in_play = key
+            .event_cards_in_play
+            .iter()
+            .map(|(i, damage)| match self.events[*i as usize].clone() {
+                EventCard::Problem(id, role_costs, damage_rule, _) => EventCard::Problem(
+                    id,
+                    role_costs,
+                    damage_rule,
+                    self.damages.get(*damage as usize).cloned(),
+                ),
+                other => other,
+            })
+            .collect();
+        gs.event_deck = expand(&self.events, &key.event_deck);
+        gs.bonus_deck = expand(&self.bonuses, &key.bonus_deck);
+        gs.damage_deck = expand(&self.damages, &key.damage_deck);
+        gs
+    }
+}
+
+/// Probability of each distinct position, packed by `Codec::pack`.
+type Distribution = HashMap<Vec<u8>, f64>;
+
+fn add(dist: &mut Distribution, codec: &Codec, key: &Position, p: f64) {
+    *dist.entry(codec.pack(key)).or_insert(0.0) += p;
+}
+
+/// Each card still in a deck, with the chance it is the one drawn.
+fn draws(deck: &[u8]) -> impl Iterator<Item = (usize, f64)> + '_ {
+    let size: u32 = deck.iter().map(|n| *n as u32).sum();
+    deck.iter()
+        .enumerate()
+        .filter(|(_, n)| **n > 0)
+        .map(move |(i, n)| (i, *n as f64 / size as f64))
+}
+
+/// Survival and final facilities, either exact or estimated by sampling.
+pub struct Analysis {
+    pub games: Option<usize>,
+    /// Chance the colony is still standing at the end of each year.
+    pub survival: Vec<f64>,
+    /// For each role, the chance of ending with each number of facilities.
+    pub facilities: BTreeMap<RoleName, BTreeMap<usize, f64>>,
+    /// Most distinct positions held at once while analysing.
+    pub states: usize,
+}
+
+/// Exact outcome probabilities of playing `years` years with `strategy`,
+/// found by following every possible draw from every reachable position.
+/// The strategy must be deterministic for the result to mean anything.
+///
+/// Positions multiply with every card drawn, so this is practical for a
+/// single year of the standard decks and several years of smaller ones such
+/// as `configs/small_deck.json`. Gives up once more than `max_positions`
+/// positions are reached at the end of a year.
+pub fn analyse(
+    config: &GameConfig,
+    strategy: &mut dyn Strategy,
+    years: usize,
+    max_positions: usize,
+) -> Result<Analysis, String> {
+    if config
+        .event_deck
+        .iter()
+        .any(|card| matches!(card, EventCard::Persistent(..)))
+    {
+        return Err("exact analysis does not follow persistent events yet".to_string());
+    }
+    if config.roles.iter().any(|role| !role.abilities.is_empty()) {
+        return Err("exact analysis does not follow role abilities yet".to_string());
+    }
+    if config
+        .rules
+        .escalation
+        .iter()
+        .any(|e| e.extra_damage_cards > 0)
+    {
+        return Err("exact analysis does not follow extra damage cards yet".to_string());
+    }
+    if config.scenario.is_stacked() {
+        return Err(
+            "exact analysis treats decks as shuffled, unstack the scenario's decks".to_string(),
+        );
+    }
+    let codec = Codec::new(config);
+    let mut dist = Distribution::new();
+    let start = codec.encode(&GameState::from_config(config));
+    add(&mut dist, &codec, &start, 1.0);
+
+    let mut survival = Vec::new();
+    let mut states = dist.len();
+    for _ in 0..years {
+        let (next, peak) = step_year(dist, &codec, strategy, max_positions)?;
+        dist = next;
+        states = states.max(peak);
+        survival.push(
+            dist.iter()
+                .filter(|(key, _)| !codec.decode(&codec.unpack(key)).collapsed())
+                .map(|(_, p)| p)
+                .sum(),
+        );
+    }
+
+    let mut facilities: BTreeMap<RoleName, BTreeMap<usize, f64>> = BTreeMap::new();
+    for (key, p) in &dist {
+        for (name, role) in codec.roles.iter().zip(&codec.unpack(key).roles) {
+            *facilities
+                .entry(name.clone())
+                .or_default()
+                .entry(role[0] as usize)
+                .or_insert(0.0) += p;
+        }
+    }
+    Ok(Analysis {
+        games: None,
+        survival,
+        facilities,
+        states,
+    })
+}
+
+/// Advances every position by one year, mirroring `GameState::play_year` with
+/// each random draw replaced by a branch per distinct card. Returns the new
+/// distribution and the most positions held at any stage.
+fn step_year(
+    dist: Distribution,
+    codec: &Codec,
+    strategy: &mut dyn Strategy,
+    max_positions: usize,
+) -> Result<(Distribution, usize), String> {
+    let mut done = Distribution::new();
+    let mut drawing = Distribution::new();
+    let peak = dist.len();
+
+    // Good phase: production, then one bonus card
+    for (packed, p) in dist {
+        let key = codec.unpack(&packed);
+        let mut gs = codec.decode(&key);
+        if gs.collapsed() {
+            done.insert(packed, p);
+            continue;
+        }
+        gs.produce();
+        if gs.bonus_deck.is_empty() {
+            let mut produced = codec.encode(&gs);
+            produced.burned = key.burned;
+            add(&mut drawing, codec, &produced, p);
+            continue;
+        }
+        for (card, chance) in draws(&key.bonus_deck) {
+            // Put the card on top and deal it as a game would, so the rules
+            // and the strategy decide who holds it
+            let mut next = gs.clone();
+            let card = take(&mut next.bonus_deck, &codec.bonuses[card]);
+            next.bonus_deck.push(card);
+            next.deal_bonus_card(strategy);
+            let mut next = codec.encode(&next);
+            next.burned = key.burned;
+            add(&mut drawing, codec, &next, p * chance);
+        }
+    }
+
+    // Event phase: one event card at a time. Damage cards are dealt with
+    // in `resolve`, once it is known which of them matter. Each position is
+    // dealt out on its own to keep the half-dealt positions few.
+    for (packed, p) in drawing {
+        let mut dealing = Distribution::new();
+        dealing.insert(packed, p);
+        while !dealing.is_empty() {
+            let mut more = Distribution::new();
+            for (packed, p) in dealing {
+                let key = codec.unpack(&packed);
+                let gs = codec.decode(&key);
+                if gs.events_dealt() >= gs.get_num_event_cards() || gs.event_deck.is_empty() {
+                    resolve(&key, p, codec, strategy, &mut done);
+                    continue;
+                }
+                for (card, chance) in draws(&key.event_deck) {
+                    let mut next = key.clone();
+                    next.event_deck[card] -= 1;
+                    next.event_cards_in_play.push((card as u8, NO_DAMAGE));
+                    add(&mut more, codec, &next, p * chance);
+                }
+            }
+            dealing = more;
+        }
+        if done.len() > max_positions {
+            return Err(format!(
+                "more than {} positions after year {}, try fewer years or a smaller deck",
+                max_positions,
+                codec.unpack(done.keys().next().unwrap()).year_number
+            ));
+        }
+    }
+    let peak = peak.max(done.len());
+    Ok((done, peak))
+}
+
+/// Plays out the rest of the year once the events are dealt, branching on
+/// the damage cards of the events that fail. Which events fail does not
+/// depend on any damage card, so a trial run with stand-in cards finds them
+/// first; the cards drawn for the other events only leave the deck.
+fn resolve(
+    key: &Position,
+    p: f64,
+    codec: &Codec,
+    strategy: &mut dyn Strategy,
+    done: &mut Distribution,
+) {
+    let gs = codec.decode(key);
+    let problems = gs
+        .event_cards_in_play
+        .iter()
+        .filter(|card| card.is_problem())
+        .count();
+    let mut trial = gs.clone();
+    attach_damage(&mut trial, codec, &vec![0; problems]);
+    trial.resolve_year(strategy);
+    let failed: Vec<bool> = trial
+        .event_log
+        .iter()
+        .filter_map(|entry| match entry {
+            LogEntry::Cancelled(..) | LogEntry::Resolved(..) => Some(false),
+            LogEntry::Failed(..) => Some(true),
+            _ => None,
+        })
+        .collect();
+
+    // Deal a damage card to each problem in turn: damage deck, burned
+    // cards, damage card per problem and the chance of getting here
+    let full_deck = counts(&codec.damages, &gs.damage_cards);
+    let mut branches = vec![(key.damage_deck.clone(), key.burned, Vec::new(), p)];
+    for fails in failed {
+        let mut next = Vec::new();
+        for (mut deck, mut burned, dealt, q) in branches {
+            if deck.iter().map(|n| *n as usize).sum::<usize>() == burned as usize {
+                deck = full_deck.clone();
+                burned = 0;
+            }
+            if !fails {
+                let mut dealt = dealt;
+                dealt.push(0);
+                next.push((deck, burned + 1, dealt, q));
+                continue;
+            }
+            for (damage, chance) in draws(&deck) {
+                let mut deck = deck.clone();
+                deck[damage] -= 1;
+                let mut dealt = dealt.clone();
+                dealt.push(damage as u8);
+                next.push((deck, burned, dealt, q * chance));
+            }
+        }
+        branches = next;
+    }
+
+    for (deck, burned, dealt, q) in branches {
+        let mut gs = gs.clone();
+        attach_damage(&mut gs, codec, &dealt);
+        gs.resolve_year(strategy);
+        // Production overwrites leftover resources and resets acted,
+        // so positions differing only in those are the same position
+        for role in gs.roles.values_mut() {
+            role.resources = 0;
+            role.acted = false;
+        }
+        let mut key = codec.encode(&gs);
+        key.damage_deck = deck;
+        key.burned = burned;
+        add(done, codec, &key, q);
+    }
+}
+
+/// Gives the problems in play the damage cards at the given `Codec` indices.
+fn attach_damage(gs: &mut GameState, codec: &Codec, dealt: &[u8]) {
+    let mut dealt = dealt.iter();
+    for card in gs.event_cards_in_play.iter_mut() {
+        if let EventCard::Problem(_, _, _, damage) = card {
+            *damage = dealt.next().map(|i| codec.damages[*i as usize].clone());
+        }
+    }
+}
+
+/// The same measures estimated from `games` random games.
+pub fn monte_carlo(
+    config: &GameConfig,
+    strategy: &mut dyn Strategy,
+    years: usize,
+    games: usize,
+) -> Analysis {
+    let mut alive = vec![0usize; years];
+    let mut counts: BTreeMap<RoleName, BTreeMap<usize, usize>> = BTreeMap::new();
+    for _ in 0..games {
+        let mut gs = GameState::from_config(config);
+        gs.verbose = false;
+        let mut seats = gs.seats(strategy);
+        for standing in alive.iter_mut() {
+            if !gs.collapsed() {
+                gs.play_year(&mut seats);
+            }
+            if !gs.collapsed() {
+                *standing += 1;
+            }
+        }
+        for role in gs.roles.values() {
+            *counts
+                .entry(role.name.clone())
+                .or_default()
+                .entry(role.facilities)
+                .or_insert(0) += 1;
+        }
+    }
+    let share = |n: usize| n as f64 / games.max(1) as f64;
+    Analysis {
+        games: Some(games),
+        survival: alive.into_iter().map(share).collect(),
+        facilities: counts
+            .into_iter()
+            .map(|(name, hist)| (name, hist.into_iter().map(|(f, n)| (f, share(n))).collect()))
+            .collect(),
+        states: 0,
+    }
+}
+
+/// Exact figures next to sampled ones, with the gap measured in standard
+/// errors of the sample so that real disagreements stand out.
+pub struct Comparison<'a>(pub &'a Analysis, pub &'a Analysis);
+
+impl<'a> fmt::Display for Comparison<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Comparison(exact, sampled) = self;
+        let games = sampled.games.unwrap_or(0).max(1) as f64;
+        let gap = |p: f64, q: f64| {
+            let se = (p * (1.0 - p) / games).sqrt();
+            if se > 0.0 {
+                (q - p) / se
+            } else {
+                0.0
+            }
+        };
+        writeln!(
+            f,
+            "Exact analysis over {} positions, against {} sampled games",
+            exact.states,
+            sampled.games.unwrap_or(0)
+        )?;
+        writeln!(f, "Survival")?;
+        writeln!(f, "  year      exact    sampled   gap (SE)")?;
+        for (year, (p, q)) in exact.survival.iter().zip(&sampled.survival).enumerate() {
+            writeln!(
+                f,
+                "  {:4}  {:8.5}  {:8.5}  {:9.2}",
+                year + 1,
+                p,
+                q,
+                gap(*p, *q)
+            )?;
+        }
+        writeln!(f, "Final facilities")?;
+        writeln!(f, "  role  count     exact   sampled   gap (SE)")?;
+        for (name, hist) in &exact.facilities {
+            for (count, p) in hist {
+                let q = sampled
+                    .facilities
+                    .get(name)
+                    .and_then(|h| h.get(count))
+                    .copied()
+                    .unwrap_or(0.0);
+                writeln!(
+                    f,
+                    "  {:4}  {:5}  {:8.4}  {:8.4}  {:9.2}",
+                    name.0,
+                    count,
+                    p,
+                    q,
+                    gap(*p, q)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
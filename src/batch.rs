@@ -0,0 +1,174 @@
+use crate::config::GameConfig;
+use crate::series::YearSnapshot;
+use crate::strategy::Strategy;
+use crate::{DamageCard, EventCardID, GameState, LogEntry, INFRA_DAMAGE_LIMIT};
+use std::fmt;
+
+/// What a batch keeps from each finished game.
+#[derive(Clone, Debug)]
+pub struct GameResult {
+    pub survived: bool,
+    pub years_played: usize,
+    pub infra_hits: usize,
+    pub repairs: usize,
+    pub events_failed: usize,
+    pub trades: usize,
+    pub facility_repairs: usize,
+    pub facility_years_lost: usize,
+    pub bonus_cards_played: usize,
+    /// Bonus cards still held when the game ended, never played.
+    pub bonus_cards_expired: usize,
+    pub history: Vec<YearSnapshot>,
+    /// Each problem card faced, and whether it failed.
+    pub card_outcomes: Vec<(EventCardID, bool)>,
+}
+
+impl GameResult {
+    pub fn from_game(gs: &GameState) -> GameResult {
+        let mut infra_hits = 0;
+        let mut repairs = 0;
+        let mut events_failed = 0;
+        let mut trades = 0;
+        let mut facility_repairs = 0;
+        let mut facility_years_lost = 0;
+        let mut bonus_cards_played = 0;
+        let mut card_outcomes = Vec::new();
+        for entry in &gs.event_log {
+            if let LogEntry::Failed(_, id, _) = entry {
+                events_failed += 1;
+                card_outcomes.push((id.clone(), true));
+            }
+            match entry {
+                LogEntry::Failed(_, _, DamageCard::InfrastructureDamage)
+                | LogEntry::ExtraDamage(_, _, DamageCard::InfrastructureDamage) => infra_hits += 1,
+                LogEntry::Traded(..) => trades += 1,
+                LogEntry::Repaired(_) => repairs += 1,
+                LogEntry::FacilityRepaired(_, _) => facility_repairs += 1,
+                LogEntry::Idle(_, _, idle) => facility_years_lost += idle,
+                LogEntry::BonusBuilt(..) => bonus_cards_played += 1,
+                LogEntry::Cancelled(_, id, _) => {
+                    bonus_cards_played += 1;
+                    card_outcomes.push((id.clone(), false))
+                }
+                LogEntry::Resolved(_, id) | LogEntry::Shielded(_, id, _) => {
+                    card_outcomes.push((id.clone(), false))
+                }
+                _ => {}
+            }
+        }
+        GameResult {
+            survived: !gs.collapsed(),
+            years_played: gs.year_number,
+            infra_hits,
+            repairs,
+            events_failed,
+            trades,
+            facility_repairs,
+            facility_years_lost,
+            bonus_cards_played,
+            bonus_cards_expired: gs.bonus_cards_held(),
+            history: gs.history.clone(),
+            card_outcomes,
+        }
+    }
+
+    /// Survived, but only because repairs kept the hits below the limit.
+    pub fn saved_by_repairs(&self) -> bool {
+        self.survived && self.infra_hits >= INFRA_DAMAGE_LIMIT
+    }
+}
+
+pub struct BatchReport {
+    pub strategy: String,
+    pub results: Vec<GameResult>,
+}
+
+impl BatchReport {
+    fn count(&self, pred: impl Fn(&GameResult) -> bool) -> usize {
+        self.results.iter().filter(|r| pred(r)).count()
+    }
+}
+
+impl fmt::Display for BatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let games = self.results.len();
+        let pct = |n: usize| 100.0 * n as f64 / games.max(1) as f64;
+        let survived = self.count(|r| r.survived);
+        let repaired = self.count(|r| r.repairs > 0);
+        let repairs: usize = self.results.iter().map(|r| r.repairs).sum();
+        let saved = self.count(GameResult::saved_by_repairs);
+        let lost_anyway = self.count(|r| !r.survived && r.repairs > 0);
+        let years: usize = self.results.iter().map(|r| r.years_played).sum();
+        let failed: usize = self.results.iter().map(|r| r.events_failed).sum();
+        let trades: usize = self.results.iter().map(|r| r.trades).sum();
+        let facility_repairs: usize = self.results.iter().map(|r| r.facility_repairs).sum();
+        let lost: usize = self.results.iter().map(|r| r.facility_years_lost).sum();
+        let played: usize = self.results.iter().map(|r| r.bonus_cards_played).sum();
+        let expired: usize = self.results.iter().map(|r| r.bonus_cards_expired).sum();
+        let wasted = self.count(|r| r.bonus_cards_expired > 0);
+
+        writeln!(f, "Strategy {}, {} games", self.strategy, games)?;
+        writeln!(
+            f,
+            "Survived:              {:6} ({:.1}%)",
+            survived,
+            pct(survived)
+        )?;
+        writeln!(
+            f,
+            "Mean years played:     {:8.2}",
+            years as f64 / games.max(1) as f64
+        )?;
+        writeln!(
+            f,
+            "Events failed:         {:6} ({:.2} per game)",
+            failed,
+            failed as f64 / games.max(1) as f64
+        )?;
+        writeln!(f, "Trades made:           {:6}", trades)?;
+        writeln!(
+            f,
+            "Games with repairs:    {:6} ({:.1}%)",
+            repaired,
+            pct(repaired)
+        )?;
+        writeln!(f, "Repairs made:          {:6}", repairs)?;
+        writeln!(f, "Saved by repairs:      {:6} ({:.1}%)", saved, pct(saved))?;
+        writeln!(
+            f,
+            "Lost despite repairs:  {:6} ({:.1}%)",
+            lost_anyway,
+            pct(lost_anyway)
+        )?;
+        writeln!(f, "Facility repairs made: {:6}", facility_repairs)?;
+        writeln!(
+            f,
+            "Facility-years lost:   {:6} ({:.2} per game)",
+            lost,
+            lost as f64 / games.max(1) as f64
+        )?;
+        writeln!(f, "Bonus cards played:    {:6}", played)?;
+        writeln!(
+            f,
+            "Bonus cards expired:   {:6} ({:.2} per game, {:.1}% of games)",
+            expired,
+            expired as f64 / games.max(1) as f64,
+            pct(wasted)
+        )
+    }
+}
+
+/// Plays `games` silent games from `config` with the same strategy.
+pub fn run_batch(config: &GameConfig, strategy: &mut dyn Strategy, games: usize) -> BatchReport {
+    let mut results = Vec::with_capacity(games);
+    for _ in 0..games {
+        let mut gs = GameState::from_config(config);
+        gs.verbose = false;
+        gs.play_game(strategy);
+        results.push(GameResult::from_game(&gs));
+    }
+    BatchReport {
+        strategy: strategy.name().to_string(),
+        results,
+    }
+}
@@ -0,0 +1,247 @@
+use crate::config::{GameConfig, Scenario};
+use crate::strategy::Strategy;
+use crate::{EventCard, GameState, INFRA_DAMAGE_LIMIT};
+use serde::Deserialize;
+use std::fmt;
+use std::fs;
+
+/// On-disk form of a `Campaign`.
+#[derive(Deserialize)]
+struct CampaignFile {
+    #[serde(default)]
+    name: String,
+    /// Config the games are played with, the standard game if omitted.
+    config: Option<String>,
+    #[serde(default)]
+    carry: Carry,
+    chapters: Vec<ChapterFile>,
+}
+
+#[derive(Deserialize)]
+struct ChapterFile {
+    name: String,
+    /// Event cards shuffled into the event deck from this chapter on.
+    #[serde(default)]
+    unlock: Vec<EventCard>,
+}
+
+/// What one game's end leaves the next game with.
+#[derive(Copy, Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Carry {
+    /// Each role starts with the facilities it had working.
+    pub facilities: bool,
+    /// Bonus cards still held are held at the start.
+    pub hands: bool,
+    pub infra_damage: bool,
+}
+
+impl Default for Carry {
+    fn default() -> Carry {
+        Carry {
+            facilities: true,
+            hands: true,
+            infra_damage: true,
+        }
+    }
+}
+
+/// Games played one after another, each set up from how the last ended.
+/// A campaign is lost with the first colony that collapses.
+pub struct Campaign {
+    pub name: String,
+    pub carry: Carry,
+    /// Each chapter's name and config, with the cards unlocked so far.
+    pub chapters: Vec<(String, GameConfig)>,
+}
+
+impl Campaign {
+    pub fn load(path: &str) -> Result<Campaign, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let file: CampaignFile =
+            serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))?;
+        Campaign::from_file(file).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    fn from_file(file: CampaignFile) -> Result<Campaign, String> {
+        let mut config = match &file.config {
+            Some(path) => GameConfig::load(path)?,
+            None => GameConfig::standard(),
+        };
+        if file.chapters.is_empty() {
+            return Err("campaign has no chapters".to_string());
+        }
+        let mut chapters = Vec::new();
+        for chapter in file.chapters {
+            config.event_deck.extend(chapter.unlock.iter().cloned());
+            config
+                .validate()
+                .map_err(|e| format!("chapter {:?}: {}", chapter.name, e))?;
+            chapters.push((chapter.name, config.clone()));
+        }
+        Ok(Campaign {
+            name: file.name,
+            carry: file.carry,
+            chapters,
+        })
+    }
+
+    /// Where the next chapter starts after a game ended as `gs`.
+    fn carried(&self, gs: &GameState) -> Scenario {
+        let mut scenario = Scenario::default();
+        for (name, role) in &gs.roles {
+            if self.carry.facilities {
+                let working = role.facilities - role.facilites_damaged;
+                scenario.facilities.insert(name.clone(), working.max(1));
+            }
+            if self.carry.hands && !role.hand.is_empty() {
+                scenario.hands.insert(name.clone(), role.hand.clone());
+            }
+        }
+        if self.carry.infra_damage {
+            scenario.infra_damage = gs.infra_damage;
+        }
+        scenario
+    }
+
+    /// Plays the campaign once, game `i` from seed `seed + i`, and returns
+    /// the games played in order. The last collapsed unless every chapter
+    /// was survived.
+    pub fn play(&self, strategy: &mut dyn Strategy, seed: u64) -> Vec<Played> {
+        let mut games: Vec<Played> = Vec::new();
+        for (i, (_, config)) in self.chapters.iter().enumerate() {
+            let mut config = config.clone();
+            if let Some(last) = games.last() {
+                config.scenario = self.carried(&last.game);
+            }
+            let seed = seed.wrapping_add(i as u64);
+            let mut gs = GameState::seeded(&config, seed);
+            gs.verbose = false;
+            let start_facilities = gs.roles.values().map(|role| role.facilities).sum();
+            let start_infra_damage = gs.infra_damage;
+            strategy.reseed(seed);
+            gs.play_game(strategy);
+            let collapsed = gs.collapsed();
+            games.push(Played {
+                start_facilities,
+                start_infra_damage,
+                game: gs,
+            });
+            if collapsed {
+                break;
+            }
+        }
+        games
+    }
+}
+
+/// A chapter's game and how it started.
+pub struct Played {
+    pub start_facilities: usize,
+    pub start_infra_damage: usize,
+    pub game: GameState,
+}
+
+/// How far a chapter's colonies got.
+#[derive(Clone, Debug, Default)]
+pub struct ChapterStats {
+    pub name: String,
+    pub reached: usize,
+    pub survived: usize,
+    /// Summed over the campaigns that reached the chapter.
+    pub infra_damage_at_start: usize,
+    pub facilities_at_start: usize,
+}
+
+pub struct CampaignReport {
+    pub name: String,
+    pub strategy: String,
+    pub campaigns: usize,
+    pub seed: u64,
+    pub chapters: Vec<ChapterStats>,
+}
+
+impl CampaignReport {
+    /// Campaigns that survived every chapter.
+    pub fn won(&self) -> usize {
+        self.chapters.last().map_or(0, |chapter| chapter.survived)
+    }
+}
+
+impl fmt::Display for CampaignReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let pct = |n: usize, of: usize| 100.0 * n as f64 / of.max(1) as f64;
+        writeln!(
+            f,
+            "Campaign {}, strategy {}, {} campaigns from seed {}",
+            self.name, self.strategy, self.campaigns, self.seed
+        )?;
+        writeln!(
+            f,
+            "  Chapter                 Reached  Survived  Start facilities  Start damage"
+        )?;
+        for (i, chapter) in self.chapters.iter().enumerate() {
+            let per = |total: usize| total as f64 / chapter.reached.max(1) as f64;
+            writeln!(
+                f,
+                "  {:2} {:20} {:6.1}%   {:6.1}%  {:16.2}  {:9.2}/{}",
+                i + 1,
+                chapter.name,
+                pct(chapter.reached, self.campaigns),
+                pct(chapter.survived, chapter.reached),
+                per(chapter.facilities_at_start),
+                per(chapter.infra_damage_at_start),
+                INFRA_DAMAGE_LIMIT
+            )?;
+        }
+        let completed: usize = self.chapters.iter().map(|c| c.survived).sum();
+        writeln!(
+            f,
+            "Campaign survival: {:.1}%",
+            pct(self.won(), self.campaigns)
+        )?;
+        writeln!(
+            f,
+            "Chapters survived: {:.2} of {} per campaign",
+            completed as f64 / self.campaigns.max(1) as f64,
+            self.chapters.len()
+        )
+    }
+}
+
+/// Plays `campaigns` campaigns, the `i`th from seed `seed + i * chapters`
+/// so that no two games share a seed.
+pub fn run_campaigns(
+    campaign: &Campaign,
+    strategy: &mut dyn Strategy,
+    campaigns: usize,
+    seed: u64,
+) -> CampaignReport {
+    let mut chapters: Vec<ChapterStats> = campaign
+        .chapters
+        .iter()
+        .map(|(name, _)| ChapterStats {
+            name: name.clone(),
+            ..ChapterStats::default()
+        })
+        .collect();
+    let stride = campaign.chapters.len() as u64;
+    for i in 0..campaigns as u64 {
+        let games = campaign.play(strategy, seed.wrapping_add(i.wrapping_mul(stride)));
+        for (stats, played) in chapters.iter_mut().zip(&games) {
+            stats.reached += 1;
+            if !played.game.collapsed() {
+                stats.survived += 1;
+            }
+            stats.infra_damage_at_start += played.start_infra_damage;
+            stats.facilities_at_start += played.start_facilities;
+        }
+    }
+    CampaignReport {
+        name: campaign.name.clone(),
+        strategy: strategy.name().to_string(),
+        campaigns,
+        seed,
+        chapters,
+    }
+}
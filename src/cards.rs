@@ -0,0 +1,244 @@
+use crate::config::GameConfig;
+use crate::report::escape;
+use crate::{BonusCard, DamageCard, DamageRule, EventCard, Opportunity, RoleName};
+use std::fmt::Write;
+use std::fs;
+
+/// Poker-size cards, nine to an A4 sheet, all in millimetres.
+const CARD_WIDTH: f64 = 63.0;
+const CARD_HEIGHT: f64 = 88.0;
+const SHEET_WIDTH: f64 = 210.0;
+const SHEET_HEIGHT: f64 = 297.0;
+const COLUMNS: usize = 3;
+const ROWS: usize = 3;
+/// Characters that fit on a line of body text.
+const LINE_LENGTH: usize = 26;
+
+/// What goes on the face of one card.
+struct Face {
+    kind: &'static str,
+    colour: &'static str,
+    title: String,
+    lines: Vec<String>,
+}
+
+fn names(roles: &[RoleName]) -> String {
+    let names: Vec<&str> = roles.iter().map(|role| role.0.as_str()).collect();
+    names.join(" or ")
+}
+
+impl Face {
+    fn event(config: &GameConfig, card: &EventCard) -> Face {
+        match card {
+            EventCard::Problem(id, role_costs, damage_rule, _)
+            | EventCard::Persistent(id, role_costs, damage_rule, _, _) => {
+                let mut lines = Vec::new();
+                for (role, cost) in role_costs {
+                    let resource = config
+                        .roles
+                        .iter()
+                        .find(|def| &def.name == cost)
+                        .map_or("resource", |def| def.resource.as_str());
+                    lines.push(format!("{} acts, {} pays 1 {}", role.0, cost.0, resource));
+                }
+                match damage_rule {
+                    Some(DamageRule::RedirectTo(role)) => {
+                        lines.push(format!("Facility damage falls on {}", role.0))
+                    }
+                    Some(DamageRule::RedirectToWeakest) => lines.push(
+                        "Facility damage falls on the role with the fewest facilities".to_string(),
+                    ),
+                    Some(DamageRule::Infrastructure) => {
+                        lines.push("All damage hits the infrastructure".to_string())
+                    }
+                    Some(DamageRule::Double) => lines.push("Damage is dealt twice".to_string()),
+                    None => {}
+                }
+                lines.push("If not dealt with, draw a damage card".to_string());
+                if let EventCard::Persistent(_, _, _, _, years) = card {
+                    lines.push(format!(
+                        "Stays in play for up to {} years until dealt with",
+                        years
+                    ));
+                }
+                Face {
+                    kind: "Event",
+                    colour: "#b5483b",
+                    title: id.clone(),
+                    lines,
+                }
+            }
+            EventCard::Opportunity(id, opportunity) => {
+                let line = match opportunity {
+                    Opportunity::Resources(role, amount) => {
+                        let resource = config
+                            .roles
+                            .iter()
+                            .find(|def| &def.name == role)
+                            .map_or("resource", |def| def.resource.as_str());
+                        format!("{} gains {} {} this year", role.0, amount, resource)
+                    }
+                    Opportunity::Build(role) => {
+                        format!("{} builds a facility for free and can still act", role.0)
+                    }
+                };
+                Face {
+                    kind: "Event",
+                    colour: "#c99a2e",
+                    title: id.clone(),
+                    lines: vec![line],
+                }
+            }
+            EventCard::NoProblem => Face {
+                kind: "Event",
+                colour: "#8a8a8a",
+                title: "All quiet".to_string(),
+                lines: vec!["Nothing happens".to_string()],
+            },
+        }
+    }
+
+    fn bonus(card: &BonusCard) -> Face {
+        match card {
+            BonusCard::Cancel(id, roles) => Face {
+                kind: "Bonus",
+                colour: "#3f8f5a",
+                title: format!("Cancel {}", id),
+                lines: vec![
+                    format!("Cancels one {} event", id),
+                    format!("{} acts", names(roles)),
+                ],
+            },
+            BonusCard::Build(role) => Face {
+                kind: "Bonus",
+                colour: "#3f8f5a",
+                title: format!("Build {}", role.0),
+                lines: vec![format!(
+                    "{} builds a facility for free if it has not acted",
+                    role.0
+                )],
+            },
+        }
+    }
+
+    fn damage(card: &DamageCard) -> Face {
+        let (title, line) = match card {
+            DamageCard::FacilityDamage(role) => (
+                format!("{} damage", role.0),
+                format!("One {} facility is damaged and produces nothing", role.0),
+            ),
+            DamageCard::FacilityDestruction(role) => (
+                format!("{} destroyed", role.0),
+                format!("{} loses a facility", role.0),
+            ),
+            DamageCard::InfrastructureDamage => (
+                "Infrastructure".to_string(),
+                "The colony takes one infrastructure damage".to_string(),
+            ),
+        };
+        Face {
+            kind: "Damage",
+            colour: "#3b4a6b",
+            title,
+            lines: vec![line],
+        }
+    }
+}
+
+/// Breaks `text` into lines of at most `LINE_LENGTH` characters where it can.
+fn wrap(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.len() + 1 + word.len() <= LINE_LENGTH => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
+    lines
+}
+
+/// The face drawn with its top left corner at (`x`, `y`).
+fn draw(svg: &mut String, face: &Face, x: f64, y: f64) {
+    write!(
+        svg,
+        r##"<g transform="translate({x} {y})"><rect x="0.5" y="0.5" width="{w}" height="{h}" rx="3" fill="white" stroke="#333" stroke-width="0.3"/><rect x="3" y="3" width="{bw}" height="14" rx="2" fill="{c}"/><text x="{cx}" y="12.5" font-size="5" font-weight="bold" text-anchor="middle" fill="white">{t}</text>"##,
+        w = CARD_WIDTH - 1.0,
+        h = CARD_HEIGHT - 1.0,
+        bw = CARD_WIDTH - 6.0,
+        c = face.colour,
+        cx = CARD_WIDTH / 2.0,
+        t = escape(&face.title),
+    )
+    .unwrap();
+    let mut line_y = 26.0;
+    for line in &face.lines {
+        for part in wrap(line) {
+            write!(
+                svg,
+                r##"<text x="6" y="{line_y}" font-size="3.6">{}</text>"##,
+                escape(&part)
+            )
+            .unwrap();
+            line_y += 5.0;
+        }
+        line_y += 2.0;
+    }
+    write!(
+        svg,
+        r##"<text x="{cx}" y="{by}" font-size="3" text-anchor="middle" fill="{c}">{k}</text></g>"##,
+        cx = CARD_WIDTH / 2.0,
+        by = CARD_HEIGHT - 5.0,
+        c = face.colour,
+        k = face.kind.to_uppercase(),
+    )
+    .unwrap();
+}
+
+/// Every card in the config's decks, one face per physical card.
+fn faces(config: &GameConfig) -> Vec<Face> {
+    let events = config
+        .event_deck
+        .iter()
+        .map(|card| Face::event(config, card));
+    let bonuses = config.bonus_deck.iter().map(Face::bonus);
+    let damages = config.damage_deck.iter().map(Face::damage);
+    events.chain(bonuses).chain(damages).collect()
+}
+
+/// The decks laid out on A4 sheets, one SVG document per sheet.
+pub fn sheets(config: &GameConfig) -> Vec<String> {
+    let per_sheet = COLUMNS * ROWS;
+    let left = (SHEET_WIDTH - COLUMNS as f64 * CARD_WIDTH) / 2.0;
+    let top = (SHEET_HEIGHT - ROWS as f64 * CARD_HEIGHT) / 2.0;
+    faces(config)
+        .chunks(per_sheet)
+        .map(|chunk| {
+            let mut svg = format!(
+                r##"<svg xmlns="http://www.w3.org/2000/svg" width="{w}mm" height="{h}mm" viewBox="0 0 {w} {h}" font-family="sans-serif">"##,
+                w = SHEET_WIDTH,
+                h = SHEET_HEIGHT,
+            );
+            for (i, face) in chunk.iter().enumerate() {
+                let x = left + (i % COLUMNS) as f64 * CARD_WIDTH;
+                let y = top + (i / COLUMNS) as f64 * CARD_HEIGHT;
+                draw(&mut svg, face, x, y);
+            }
+            svg.push_str("</svg>\n");
+            svg
+        })
+        .collect()
+}
+
+/// Writes the sheets to `dir` as `sheet-01.svg` and on, returning how many.
+pub fn write_sheets(config: &GameConfig, dir: &str) -> Result<usize, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir, e))?;
+    let sheets = sheets(config);
+    for (i, sheet) in sheets.iter().enumerate() {
+        let path = format!("{}/sheet-{:02}.svg", dir, i + 1);
+        fs::write(&path, sheet).map_err(|e| format!("{}: {}", path, e))?;
+    }
+    Ok(sheets.len())
+}
@@ -0,0 +1,204 @@
+use crate::batch::GameResult;
+use crate::config::GameConfig;
+use crate::strategy::Strategy;
+use crate::GameState;
+use std::fmt;
+
+/// Two-sided significance level below which a difference is trusted.
+const SIGNIFICANCE: f64 = 0.05;
+/// Normal quantile for 95% confidence intervals.
+const Z_95: f64 = 1.96;
+/// Standard errors a true difference must span to be found four times in
+/// five at `SIGNIFICANCE` (1.96 + 0.84).
+const Z_POWER: f64 = 2.8;
+
+/// A measure compared over paired games: game `i` of both configurations is
+/// played from the same seed, so luck of the draw cancels out of the
+/// per-game differences.
+pub struct Difference {
+    pub name: &'static str,
+    pub mean_a: f64,
+    pub mean_b: f64,
+    /// Standard error of the mean per-game difference.
+    pub se: f64,
+    /// Standard deviation of the per-game differences.
+    sd: f64,
+    percent: bool,
+}
+
+impl Difference {
+    pub fn paired(name: &'static str, percent: bool, a: &[f64], b: &[f64]) -> Difference {
+        let n = a.len().max(1) as f64;
+        let diffs: Vec<f64> = a.iter().zip(b).map(|(a, b)| b - a).collect();
+        let mean = diffs.iter().sum::<f64>() / n;
+        let var = diffs.iter().map(|d| (d - mean).powi(2)).sum::<f64>() / (n - 1.0).max(1.0);
+        Difference {
+            name,
+            mean_a: a.iter().sum::<f64>() / n,
+            mean_b: b.iter().sum::<f64>() / n,
+            se: (var / n).sqrt(),
+            sd: var.sqrt(),
+            percent,
+        }
+    }
+
+    pub fn diff(&self) -> f64 {
+        self.mean_b - self.mean_a
+    }
+
+    /// 95% confidence interval of `diff`.
+    pub fn interval(&self) -> (f64, f64) {
+        (self.diff() - Z_95 * self.se, self.diff() + Z_95 * self.se)
+    }
+
+    /// Two-sided p-value of a paired z-test that the configurations are
+    /// the same. Exactly 1 when no game came out differently.
+    pub fn p_value(&self) -> f64 {
+        if self.se == 0.0 {
+            return if self.diff() == 0.0 { 1.0 } else { 0.0 };
+        }
+        erfc((self.diff() / self.se).abs() / 2f64.sqrt())
+    }
+
+    pub fn significant(&self) -> bool {
+        self.p_value() < SIGNIFICANCE
+    }
+
+    /// Paired games needed to reliably detect a difference as large as the
+    /// one seen.
+    pub fn games_needed(&self) -> Option<usize> {
+        if self.diff() == 0.0 {
+            return None;
+        }
+        Some(((Z_POWER * self.sd / self.diff()).powi(2)).ceil() as usize)
+    }
+
+    fn show(&self, value: f64, signed: bool) -> String {
+        match (self.percent, signed) {
+            (true, true) => format!("{:+.1}%", 100.0 * value),
+            (true, false) => format!("{:.1}%", 100.0 * value),
+            (false, true) => format!("{:+.2}", value),
+            (false, false) => format!("{:.2}", value),
+        }
+    }
+}
+
+/// Complementary error function, from Abramowitz and Stegun 7.1.26.
+/// Accurate to about 1e-7, plenty for a p-value.
+fn erfc(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let poly = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    poly * (-x * x).exp()
+}
+
+pub struct Comparison {
+    pub strategy: String,
+    pub games: usize,
+    pub seed: u64,
+    pub survival: Difference,
+    pub years_lasted: Difference,
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Strategy {}, {} paired games from seed {}",
+            self.strategy, self.games, self.seed
+        )?;
+        writeln!(
+            f,
+            "                      A        B    B - A   95% interval         p"
+        )?;
+        for d in [&self.survival, &self.years_lasted] {
+            let (low, high) = d.interval();
+            writeln!(
+                f,
+                "{:<14} {:>8} {:>8} {:>8}   [{}, {}] {:>9.4}",
+                d.name,
+                d.show(d.mean_a, false),
+                d.show(d.mean_b, false),
+                d.show(d.diff(), true),
+                d.show(low, true),
+                d.show(high, true),
+                d.p_value()
+            )?;
+        }
+        for d in [&self.survival, &self.years_lasted] {
+            if d.significant() {
+                continue;
+            }
+            match d.games_needed() {
+                None => writeln!(f, "{}: no difference in any game", d.name)?,
+                Some(needed) => writeln!(
+                    f,
+                    "{}: difference too small to trust, about {} games would show it",
+                    d.name, needed
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Plays `games` games of each configuration, game `i` of both from seed
+/// `seed + i` with the strategy reseeded the same way.
+pub fn compare(
+    a: &GameConfig,
+    b: &GameConfig,
+    strategy: &mut dyn Strategy,
+    games: usize,
+    seed: u64,
+) -> Comparison {
+    let mut play = |config: &GameConfig, game: u64| {
+        let mut gs = GameState::seeded(config, seed.wrapping_add(game));
+        gs.verbose = false;
+        strategy.reseed(seed.wrapping_add(game));
+        gs.play_game(strategy);
+        GameResult::from_game(&gs)
+    };
+    let mut survived = (Vec::new(), Vec::new());
+    let mut years = (Vec::new(), Vec::new());
+    for game in 0..games as u64 {
+        let (ra, rb) = (play(a, game), play(b, game));
+        survived.0.push(ra.survived as u8 as f64);
+        survived.1.push(rb.survived as u8 as f64);
+        years.0.push(ra.years_played as f64);
+        years.1.push(rb.years_played as f64);
+    }
+    Comparison {
+        strategy: strategy.name().to_string(),
+        games,
+        seed,
+        survival: Difference::paired("Survival", true, &survived.0, &survived.1),
+        years_lasted: Difference::paired("Years lasted", false, &years.0, &years.1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paired_difference_statistics_match_hand_worked_values() {
+        assert!((erfc(1.96 / 2f64.sqrt()) - 0.05).abs() < 1e-4);
+        assert!((erfc(0.0) - 1.0).abs() < 1e-6);
+
+        let same = Difference::paired("Same", false, &[1.0, 0.0, 1.0], &[1.0, 0.0, 1.0]);
+        assert_eq!(same.p_value(), 1.0);
+        assert_eq!(same.interval(), (0.0, 0.0));
+        assert_eq!(same.games_needed(), None);
+
+        // Differences 1, 0, 1, 2: mean 1, variance 2/3, standard error
+        // sqrt(2/3 / 4), so z = sqrt(6).
+        let d = Difference::paired("Hand", false, &[0.0; 4], &[1.0, 0.0, 1.0, 2.0]);
+        assert_eq!(d.diff(), 1.0);
+        let (low, high) = d.interval();
+        assert!((low - 0.19983).abs() < 1e-4 && (high - 1.80017).abs() < 1e-4);
+        assert!((d.p_value() - 0.014306).abs() < 1e-5);
+        assert!(d.significant());
+        assert_eq!(d.games_needed(), Some(6));
+    }
+}
@@ -0,0 +1,716 @@
+use crate::{Ability, BonusCard, DamageCard, DamageRule, EventCard, Opportunity, RoleName, Rules};
+use crate::{INFRA_DAMAGE_LIMIT, YEARS_PER_GAME};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Debug;
+use std::fs;
+
+/// Smallest and largest colony the simulator will set up.
+const MIN_ROLES: usize = 2;
+const MAX_ROLES: usize = 6;
+
+/// A colony department as written in a config file.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct RoleDef {
+    pub name: RoleName,
+    #[serde(default = "default_starting_facilities")]
+    pub starting_facilities: usize,
+    #[serde(default = "default_facility_cap")]
+    pub facility_cap: usize,
+    /// What the role's facilities produce, e.g. "energy". Only used for display.
+    pub resource: String,
+    #[serde(default)]
+    pub abilities: Vec<Ability>,
+    /// What each working facility produces, one entry per facility up to
+    /// the cap. Empty means one resource each.
+    #[serde(default)]
+    pub production: Vec<usize>,
+    /// What every role pays to build this role's next facility, one entry
+    /// per level below the cap: the first takes it from one facility to
+    /// two. Empty means one resource each.
+    #[serde(default)]
+    pub build_cost: Vec<usize>,
+}
+
+impl RoleDef {
+    pub fn production_table(&self) -> Vec<usize> {
+        if self.production.is_empty() {
+            vec![1; self.facility_cap]
+        } else {
+            self.production.clone()
+        }
+    }
+
+    pub fn build_cost_table(&self) -> Vec<usize> {
+        if self.build_cost.is_empty() {
+            vec![1; self.facility_cap.saturating_sub(1)]
+        } else {
+            self.build_cost.clone()
+        }
+    }
+}
+
+fn default_starting_facilities() -> usize {
+    1
+}
+
+fn default_facility_cap() -> usize {
+    4
+}
+
+/// A starting position other than a new game's, for tutorials and for
+/// trying how a colony recovers from a disaster. Cards named here are taken
+/// out of the config's decks, so a scenario never adds cards to the game.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scenario {
+    /// Facilities per role, in place of the role's `starting_facilities`.
+    pub facilities: BTreeMap<RoleName, usize>,
+    pub infra_damage: usize,
+    /// Bonus cards already in each role's hand.
+    pub hands: BTreeMap<RoleName, Vec<BonusCard>>,
+    /// Cards put on top of each deck after shuffling, the first drawn first.
+    pub event_deck_top: Vec<EventCard>,
+    pub bonus_deck_top: Vec<BonusCard>,
+    pub damage_deck_top: Vec<DamageCard>,
+    /// Years already played. The game still ends after `YEARS_PER_GAME`.
+    pub year_number: usize,
+}
+
+impl Scenario {
+    pub fn load(path: &str) -> Result<Scenario, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Whether any deck has cards stacked on top, fixing part of its order.
+    pub fn is_stacked(&self) -> bool {
+        !self.event_deck_top.is_empty()
+            || !self.bonus_deck_top.is_empty()
+            || !self.damage_deck_top.is_empty()
+    }
+}
+
+/// Everything needed to set up a game: the roles at the table, the three
+/// decks, the rule variants in force and where the game starts from.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize)]
+pub struct GameConfig {
+    pub roles: Vec<RoleDef>,
+    pub event_deck: Vec<EventCard>,
+    pub bonus_deck: Vec<BonusCard>,
+    pub damage_deck: Vec<DamageCard>,
+    pub rules: Rules,
+    pub scenario: Scenario,
+}
+
+/// On-disk form of a `GameConfig`. Omitted roles and decks fall back to the
+/// standard ones, except the damage deck which is generated from the roles.
+#[derive(Deserialize)]
+struct ConfigFile {
+    roles: Option<Vec<RoleDef>>,
+    event_deck: Option<Vec<EventCard>>,
+    bonus_deck: Option<Vec<BonusCard>>,
+    damage_deck: Option<Vec<DamageCard>>,
+    #[serde(default)]
+    rules: Rules,
+    #[serde(default)]
+    scenario: Scenario,
+}
+
+impl GameConfig {
+    /// The Hab/Joul/Man colony with the printed decks.
+    pub fn standard() -> GameConfig {
+        let roles = standard_roles();
+        GameConfig {
+            damage_deck: damage_deck_for(&roles),
+            roles,
+            event_deck: standard_event_deck(),
+            bonus_deck: standard_bonus_deck(),
+            rules: Rules::default(),
+            scenario: Scenario::default(),
+        }
+    }
+
+    pub fn load(path: &str) -> Result<GameConfig, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        GameConfig::from_json(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn from_json(text: &str) -> Result<GameConfig, String> {
+        let file: ConfigFile = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        let roles = file.roles.unwrap_or_else(standard_roles);
+        let damage_deck = match file.damage_deck {
+            Some(deck) => deck,
+            None => damage_deck_for(&roles),
+        };
+        let config = GameConfig {
+            damage_deck,
+            event_deck: file.event_deck.unwrap_or_else(standard_event_deck),
+            bonus_deck: file.bonus_deck.unwrap_or_else(standard_bonus_deck),
+            roles,
+            rules: file.rules,
+            scenario: file.scenario,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Starts games from the scenario in `path` instead.
+    pub fn with_scenario(mut self, path: &str) -> Result<GameConfig, String> {
+        self.scenario = Scenario::load(path)?;
+        self.validate().map_err(|e| format!("{}: {}", path, e))?;
+        Ok(self)
+    }
+
+    /// Checks the role count, that every card names a defined role and that
+    /// the scenario fits the roles and decks.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.roles.len() < MIN_ROLES || self.roles.len() > MAX_ROLES {
+            return Err(format!(
+                "{} roles defined, games need {} to {}",
+                self.roles.len(),
+                MIN_ROLES,
+                MAX_ROLES
+            ));
+        }
+        let mut names = HashSet::new();
+        for role in &self.roles {
+            if !names.insert(&role.name) {
+                return Err(format!("role {:?} defined twice", role.name));
+            }
+            if role.starting_facilities < 1 || role.starting_facilities > role.facility_cap {
+                return Err(format!(
+                    "role {:?} starts with {} facilities but its cap is {}",
+                    role.name, role.starting_facilities, role.facility_cap
+                ));
+            }
+            if !role.production.is_empty() && role.production.len() != role.facility_cap {
+                return Err(format!(
+                    "role {:?} has production for {} facilities but its cap is {}",
+                    role.name,
+                    role.production.len(),
+                    role.facility_cap
+                ));
+            }
+            if !role.build_cost.is_empty() && role.build_cost.len() + 1 != role.facility_cap {
+                return Err(format!(
+                    "role {:?} has {} build costs but needs one per level below its cap of {}",
+                    role.name,
+                    role.build_cost.len(),
+                    role.facility_cap
+                ));
+            }
+            if role.abilities.contains(&Ability::ExtraResource(0)) {
+                return Err(format!(
+                    "role {:?} needs at least one working facility for an extra resource",
+                    role.name
+                ));
+            }
+        }
+        let check = |name: &RoleName, card: &dyn std::fmt::Debug| {
+            if names.contains(name) {
+                Ok(())
+            } else {
+                Err(format!("{:?} refers to undefined role {:?}", card, name))
+            }
+        };
+        for card in &self.event_deck {
+            match card {
+                EventCard::Problem(_, role_costs, damage_rule, _)
+                | EventCard::Persistent(_, role_costs, damage_rule, _, _) => {
+                    for (affected, cost) in role_costs {
+                        check(affected, card)?;
+                        check(cost, card)?;
+                    }
+                    if let Some(DamageRule::RedirectTo(role)) = damage_rule {
+                        check(role, card)?;
+                    }
+                }
+                EventCard::Opportunity(_, Opportunity::Resources(role, _))
+                | EventCard::Opportunity(_, Opportunity::Build(role)) => check(role, card)?,
+                EventCard::NoProblem => {}
+            }
+            if let EventCard::Persistent(_, _, _, _, 0) = card {
+                return Err(format!(
+                    "{:?} must stay in play for at least one year",
+                    card
+                ));
+            }
+        }
+        for card in &self.bonus_deck {
+            match card {
+                BonusCard::Cancel(_, roles) => {
+                    for role in roles {
+                        check(role, card)?;
+                    }
+                }
+                BonusCard::Build(role) => check(role, card)?,
+            }
+        }
+        if let Some(rate) = self.rules.trade_rate {
+            if rate.give == 0 || rate.receive == 0 {
+                return Err("trade rate must give and receive at least one resource".to_string());
+            }
+        }
+        for escalation in &self.rules.escalation {
+            for id in &escalation.events {
+                if !self.event_deck.iter().any(|card| card.id() == Some(id)) {
+                    return Err(format!("escalation refers to unknown event {:?}", id));
+                }
+            }
+        }
+        for role in &self.rules.repair_cost {
+            if !names.contains(role) {
+                return Err(format!("repair cost refers to undefined role {:?}", role));
+            }
+        }
+        for card in &self.damage_deck {
+            match card {
+                DamageCard::FacilityDamage(role) | DamageCard::FacilityDestruction(role) => {
+                    check(role, card)?
+                }
+                DamageCard::InfrastructureDamage => {}
+            }
+        }
+        self.validate_scenario()
+    }
+
+    fn validate_scenario(&self) -> Result<(), String> {
+        let scenario = &self.scenario;
+        for (name, &facilities) in &scenario.facilities {
+            let role = self
+                .roles
+                .iter()
+                .find(|role| &role.name == name)
+                .ok_or_else(|| format!("scenario refers to undefined role {:?}", name))?;
+            if facilities < 1 || facilities > role.facility_cap {
+                return Err(format!(
+                    "scenario gives role {:?} {} facilities but its cap is {}",
+                    name, facilities, role.facility_cap
+                ));
+            }
+        }
+        if scenario.infra_damage >= INFRA_DAMAGE_LIMIT {
+            return Err(format!(
+                "scenario starts with {} infrastructure damage, the colony collapses at {}",
+                scenario.infra_damage, INFRA_DAMAGE_LIMIT
+            ));
+        }
+        if scenario.year_number >= YEARS_PER_GAME {
+            return Err(format!(
+                "scenario starts in year {} but games last {} years",
+                scenario.year_number, YEARS_PER_GAME
+            ));
+        }
+        for (name, hand) in &scenario.hands {
+            if !self.roles.iter().any(|role| &role.name == name) {
+                return Err(format!("scenario refers to undefined role {:?}", name));
+            }
+            match self.rules.hand_limit {
+                Some(limit) if hand.len() > limit => {
+                    return Err(format!(
+                        "scenario gives role {:?} {} bonus cards but the hand limit is {}",
+                        name,
+                        hand.len(),
+                        limit
+                    ))
+                }
+                _ => {}
+            }
+        }
+        let bonus_cards: Vec<&BonusCard> = scenario
+            .hands
+            .values()
+            .flatten()
+            .chain(&scenario.bonus_deck_top)
+            .collect();
+        in_deck(&bonus_cards, &self.bonus_deck, "bonus")?;
+        in_deck(
+            &scenario.event_deck_top.iter().collect::<Vec<_>>(),
+            &self.event_deck,
+            "event",
+        )?;
+        in_deck(
+            &scenario.damage_deck_top.iter().collect::<Vec<_>>(),
+            &self.damage_deck,
+            "damage",
+        )
+    }
+}
+
+/// Checks the deck holds at least as many of each card as `cards` asks for.
+fn in_deck<T: PartialEq + Debug>(cards: &[&T], deck: &[T], name: &str) -> Result<(), String> {
+    for card in cards {
+        let wanted = cards.iter().filter(|c| c == &card).count();
+        let held = deck.iter().filter(|c| c == card).count();
+        if wanted > held {
+            return Err(format!(
+                "scenario uses {} of {:?} but the {} deck has {}",
+                wanted, card, name, held
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn standard_roles() -> Vec<RoleDef> {
+    vec![
+        RoleDef {
+            name: hab(),
+            starting_facilities: 1,
+            facility_cap: 4,
+            resource: "habitat".to_string(),
+            abilities: Vec::new(),
+            production: Vec::new(),
+            build_cost: Vec::new(),
+        },
+        RoleDef {
+            name: joul(),
+            starting_facilities: 1,
+            facility_cap: 4,
+            resource: "energy".to_string(),
+            abilities: Vec::new(),
+            production: Vec::new(),
+            build_cost: Vec::new(),
+        },
+        RoleDef {
+            name: man(),
+            starting_facilities: 1,
+            facility_cap: 4,
+            resource: "materials".to_string(),
+            abilities: Vec::new(),
+            production: Vec::new(),
+            build_cost: Vec::new(),
+        },
+    ]
+}
+
+fn hab() -> RoleName {
+    RoleName::from("Hab")
+}
+
+fn joul() -> RoleName {
+    RoleName::from("Joul")
+}
+
+fn man() -> RoleName {
+    RoleName::from("Man")
+}
+
+/// Three infrastructure hits plus one damage and one destruction per role.
+pub fn damage_deck_for(roles: &[RoleDef]) -> Vec<DamageCard> {
+    let mut damage_deck = vec![
+        DamageCard::InfrastructureDamage,
+        DamageCard::InfrastructureDamage,
+        DamageCard::InfrastructureDamage,
+    ];
+    for role in roles {
+        damage_deck.push(DamageCard::FacilityDamage(role.name.clone()));
+    }
+    for role in roles {
+        damage_deck.push(DamageCard::FacilityDestruction(role.name.clone()));
+    }
+    damage_deck
+}
+
+fn standard_event_deck() -> Vec<EventCard> {
+    vec![
+        //Spacecold issue, hab cost
+        EventCard::Problem(
+            "Spacecold".to_string(),
+            vec![(hab(), hab())],
+            Some(DamageRule::RedirectTo(hab())),
+            None,
+        ),
+        //Raiding issue, hab cost
+        EventCard::Problem(
+            "Spacecold".to_string(),
+            vec![(hab(), man())],
+            Some(DamageRule::RedirectTo(hab())),
+            None,
+        ),
+        //Raiding issue, man cost
+        EventCard::Problem(
+            "Spacecold".to_string(),
+            vec![(hab(), joul())],
+            Some(DamageRule::RedirectTo(hab())),
+            None,
+        ),
+        //Mutiny issue, hab cost
+        EventCard::Problem("Mutiny".to_string(), vec![(hab(), hab())], None, None),
+        //Nanofab issue, hab cost
+        EventCard::Problem("Mutiny".to_string(), vec![(hab(), hab())], None, None),
+        //Nanofab issue, man cost
+        EventCard::Problem("Mutiny".to_string(), vec![(hab(), hab())], None, None),
+        //Nanofab issue, energy cost
+        EventCard::Problem("Nanobug".to_string(), vec![(man(), joul())], None, None),
+        //Nanofab issue, hab cost
+        EventCard::Problem("Nanobug".to_string(), vec![(man(), hab())], None, None),
+        //Nanofab issue, man cost
+        EventCard::Problem("Nanobug".to_string(), vec![(man(), man())], None, None),
+        //Hab issue, energy cost
+        EventCard::Problem("Raiding".to_string(), vec![(hab(), joul())], None, None),
+        //Hab issue, hab cost
+        EventCard::Problem("Raiding".to_string(), vec![(hab(), hab())], None, None),
+        //Hab issue, man cost
+        EventCard::Problem("Raiding".to_string(), vec![(hab(), man())], None, None),
+        //Joul issue, energy cost, Joul Override
+        EventCard::Problem(
+            "Surge".to_string(),
+            vec![(joul(), joul())],
+            Some(DamageRule::RedirectTo(joul())),
+            None,
+        ),
+        //Joul issue, hab cost, Joul
+        EventCard::Problem(
+            "Surge".to_string(),
+            vec![(joul(), hab())],
+            Some(DamageRule::RedirectTo(joul())),
+            None,
+        ),
+        //Nanofab issue, man cost, Joul override
+        EventCard::Problem(
+            "Surge".to_string(),
+            vec![(joul(), man())],
+            Some(DamageRule::RedirectTo(joul())),
+            None,
+        ),
+        //Joul/Man issue, man/hab cost - meteor
+        EventCard::Problem(
+            "Meteor".to_string(),
+            vec![(joul(), man()), (man(), hab())],
+            None,
+            None,
+        ),
+        //Joul/Man issue, man/hab cost - meteor
+        EventCard::Problem(
+            "Meteor".to_string(),
+            vec![(joul(), hab()), (man(), joul())],
+            None,
+            None,
+        ),
+        //Joul/Man issue, man/hab cost - meteor
+        EventCard::Problem(
+            "Meteor".to_string(),
+            vec![(joul(), joul()), (man(), man())],
+            None,
+            None,
+        ),
+        //Joul/Man issue, man/hab cost - quake
+        EventCard::Problem(
+            "Quake".to_string(),
+            vec![(joul(), man()), (man(), hab())],
+            None,
+            None,
+        ),
+        //Joul/Man issue, man/hab cost - quake
+        EventCard::Problem(
+            "Quake".to_string(),
+            vec![(joul(), hab()), (man(), joul())],
+            None,
+            None,
+        ),
+        //Joul/Man issue, man/hab cost - quake
+        EventCard::Problem(
+            "Quake".to_string(),
+            vec![(joul(), joul()), (man(), man())],
+            None,
+            None,
+        ),
+        //Joul/Man/Hab issue, Joul/man/hab cost - system
+        EventCard::Problem(
+            "Systemic".to_string(),
+            vec![(joul(), joul()), (man(), man()), (hab(), hab())],
+            None,
+            None,
+        ),
+        //Joul/Man/Hab issue, man/hab/joul cost - system
+        EventCard::Problem(
+            "Systemic".to_string(),
+            vec![(joul(), man()), (man(), hab()), (hab(), joul())],
+            None,
+            None,
+        ),
+        //Joul/Man/Hab issue, Joul/man/hab cost - system
+        EventCard::Problem(
+            "Systemic".to_string(),
+            vec![(joul(), hab()), (man(), joul()), (hab(), man())],
+            None,
+            None,
+        ),
+        EventCard::NoProblem,
+        EventCard::NoProblem,
+        EventCard::NoProblem,
+    ]
+}
+
+fn standard_bonus_deck() -> Vec<BonusCard> {
+    vec![
+        BonusCard::Build(man()),
+        BonusCard::Build(man()),
+        BonusCard::Build(hab()),
+        BonusCard::Build(hab()),
+        BonusCard::Build(joul()),
+        BonusCard::Build(joul()),
+        BonusCard::Cancel("Systemic".to_string(), vec![hab(), joul(), man()]),
+        BonusCard::Cancel("Systemic".to_string(), vec![hab(), joul(), man()]),
+        BonusCard::Cancel("Quake".to_string(), vec![joul(), man()]),
+        BonusCard::Cancel("Quake".to_string(), vec![joul(), man()]),
+        BonusCard::Cancel("Meteor".to_string(), vec![joul(), man()]),
+        BonusCard::Cancel("Meteor".to_string(), vec![joul(), man()]),
+        BonusCard::Cancel("Mutiny".to_string(), vec![hab()]),
+        BonusCard::Cancel("Mutiny".to_string(), vec![hab()]),
+        BonusCard::Cancel("Raiding".to_string(), vec![hab()]),
+        BonusCard::Cancel("Raiding".to_string(), vec![hab()]),
+        BonusCard::Cancel("Spacecold".to_string(), vec![hab()]),
+        BonusCard::Cancel("Spacecold".to_string(), vec![hab()]),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GameState;
+
+    fn recovery() -> GameConfig {
+        GameConfig::standard()
+            .with_scenario("configs/scenarios/recovery.json")
+            .unwrap()
+    }
+
+    #[test]
+    fn scenario_sets_up_facilities_hands_and_stacked_decks() {
+        let mut config = recovery();
+        let man = RoleName::from("Man");
+        let held = config.scenario.hands[&man][0].clone();
+        let bonus_top: Vec<BonusCard> = config
+            .bonus_deck
+            .iter()
+            .filter(|&card| card != &held)
+            .take(2)
+            .cloned()
+            .collect();
+        config.scenario.bonus_deck_top = bonus_top.clone();
+        config.scenario.damage_deck_top = config.damage_deck[..2].to_vec();
+        config.validate().unwrap();
+
+        for seed in 0..5 {
+            let gs = GameState::seeded(&config, seed);
+            assert_eq!(gs.year_number, 4);
+            assert_eq!(gs.infra_damage, 2);
+            let facilities: Vec<usize> = gs.roles.values().map(|role| role.facilities).collect();
+            assert_eq!(facilities, [3, 2, 1]);
+            assert_eq!(gs.roles[&man].hand, std::slice::from_ref(&held));
+            assert!(gs
+                .roles
+                .values()
+                .filter(|role| role.name != man)
+                .all(|role| role.hand.is_empty()));
+            assert_eq!(gs.bonus_deck.len() + 1, config.bonus_deck.len());
+            assert_eq!(gs.event_deck.len(), config.event_deck.len());
+
+            // Decks are drawn from the end, so the first stacked card is last.
+            let events: Vec<EventCard> = gs.event_deck.iter().rev().take(2).cloned().collect();
+            assert_eq!(events, config.scenario.event_deck_top);
+            let bonus: Vec<BonusCard> = gs.bonus_deck.iter().rev().take(2).cloned().collect();
+            assert_eq!(bonus, bonus_top);
+            let damage: Vec<DamageCard> = gs.damage_deck.iter().rev().take(2).cloned().collect();
+            assert_eq!(damage, config.scenario.damage_deck_top);
+        }
+    }
+
+    #[test]
+    fn scenario_that_does_not_fit_the_game_is_rejected() {
+        let man = RoleName::from("Man");
+        let held = recovery().scenario.hands[&man][0].clone();
+        type Change = Box<dyn Fn(&mut GameConfig)>;
+        let cases: Vec<(&str, Change)> = vec![
+            (
+                "undefined role Bio",
+                Box::new(|c| {
+                    c.scenario.facilities.insert(RoleName::from("Bio"), 1);
+                }),
+            ),
+            (
+                "0 facilities but its cap is",
+                Box::new(|c| {
+                    c.scenario.facilities.insert(RoleName::from("Hab"), 0);
+                }),
+            ),
+            (
+                "facilities but its cap is",
+                Box::new(|c| {
+                    let cap = c.roles[0].facility_cap;
+                    c.scenario
+                        .facilities
+                        .insert(c.roles[0].name.clone(), cap + 1);
+                }),
+            ),
+            (
+                "the colony collapses at",
+                Box::new(|c| {
+                    c.scenario.infra_damage = INFRA_DAMAGE_LIMIT;
+                }),
+            ),
+            (
+                "games last",
+                Box::new(|c| c.scenario.year_number = YEARS_PER_GAME),
+            ),
+            (
+                "undefined role Bio",
+                Box::new(|c| {
+                    c.scenario.hands.insert(RoleName::from("Bio"), Vec::new());
+                }),
+            ),
+            (
+                "2 bonus cards but the hand limit is 1",
+                Box::new(move |c| {
+                    c.rules.hand_limit = Some(1);
+                    c.scenario
+                        .hands
+                        .get_mut(&man)
+                        .unwrap()
+                        .push(c.bonus_deck[0].clone());
+                }),
+            ),
+            (
+                "but the event deck has",
+                Box::new(|c| {
+                    let top = c.scenario.event_deck_top[0].clone();
+                    c.event_deck.retain(|card| card != &top);
+                }),
+            ),
+            (
+                "but the bonus deck has",
+                Box::new(move |c| {
+                    let copies = c.bonus_deck.iter().filter(|&card| card == &held).count();
+                    c.scenario.bonus_deck_top = vec![held.clone(); copies];
+                }),
+            ),
+        ];
+        for (expected, change) in cases {
+            let mut config = recovery();
+            change(&mut config);
+            let err = config.validate().unwrap_err();
+            assert!(
+                err.contains(expected),
+                "{:?} does not mention {:?}",
+                err,
+                expected
+            );
+        }
+
+        let mut config = recovery();
+        config.rules.hand_limit = Some(1);
+        assert_eq!(config.validate(), Ok(()));
+
+        let err = GameConfig::standard()
+            .with_scenario("configs/scenarios/missing.json")
+            .unwrap_err();
+        assert!(
+            err.starts_with("configs/scenarios/missing.json: "),
+            "{}",
+            err
+        );
+    }
+}
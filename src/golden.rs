@@ -0,0 +1,165 @@
+use crate::config::GameConfig;
+use crate::strategy;
+use crate::GameState;
+use std::fmt;
+use std::fs;
+
+/// Directory the recorded games are kept in.
+pub const DIR: &str = "golden";
+
+/// A recorded game: the seed, config and strategy it was played with and
+/// its event log, one entry per line, with the colony at the end of each
+/// year after that year's entries. Replaying it after a rules change shows
+/// what the change did as a diff of the log, even when the change only
+/// moves resources or facilities.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Golden {
+    /// Path of the config, or `None` for the standard game.
+    pub config: Option<String>,
+    pub seed: u64,
+    pub strategy: String,
+    pub log: Vec<String>,
+}
+
+impl Golden {
+    /// Plays the game and records its log.
+    pub fn record(config: Option<&str>, seed: u64, strategy: &str) -> Result<Golden, String> {
+        let mut golden = Golden {
+            config: config.map(str::to_string),
+            seed,
+            strategy: strategy.to_string(),
+            log: Vec::new(),
+        };
+        golden.log = golden.play()?;
+        Ok(golden)
+    }
+
+    pub fn load(path: &str) -> Result<Golden, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        Golden::parse(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.to_string()).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Reads the header lines `config`, `seed` and `strategy`, then after a
+    /// blank line the log.
+    fn parse(text: &str) -> Result<Golden, String> {
+        let mut lines = text.lines();
+        let mut field = |name: &str| -> Result<String, String> {
+            let line = lines.next().unwrap_or("");
+            match line.strip_prefix(name) {
+                Some(value) if value.starts_with(' ') => Ok(value.trim().to_string()),
+                _ => Err(format!("expected {} line, got {:?}", name, line)),
+            }
+        };
+        let config = match field("config")?.as_str() {
+            "standard" => None,
+            path => Some(path.to_string()),
+        };
+        let seed = field("seed")?;
+        let seed = seed
+            .parse()
+            .map_err(|_| format!("seed expects a number, got {}", seed))?;
+        let strategy = field("strategy")?;
+        match lines.next() {
+            Some("") => {}
+            other => return Err(format!("expected a blank line, got {:?}", other)),
+        }
+        Ok(Golden {
+            config,
+            seed,
+            strategy,
+            log: lines.map(str::to_string).collect(),
+        })
+    }
+
+    /// The log the game gives now.
+    pub fn play(&self) -> Result<Vec<String>, String> {
+        let config = match &self.config {
+            Some(path) => GameConfig::load(path)?,
+            None => GameConfig::standard(),
+        };
+        let mut strategy = strategy::by_name(&self.strategy)
+            .ok_or_else(|| format!("unknown strategy {}", self.strategy))?;
+        strategy.reseed(self.seed);
+        let mut gs = GameState::seeded(&config, self.seed);
+        gs.verbose = false;
+        gs.play_game(strategy.as_mut());
+        let mut log = Vec::new();
+        for year in 0..gs.year_number {
+            log.extend(
+                gs.event_log
+                    .iter()
+                    .filter(|entry| entry.year() == year)
+                    .map(|entry| format!("{:?}", entry)),
+            );
+            if let Some(snapshot) = gs.history.iter().find(|s| s.year_number == year + 1) {
+                log.push(format!("{:?}", snapshot));
+            }
+        }
+        Ok(log)
+    }
+
+    /// The first log entry, counted from 1, where the game now differs from
+    /// the recording, with the recorded and the new entry.
+    pub fn first_difference(&self) -> Result<Option<(usize, String, String)>, String> {
+        let log = self.play()?;
+        let lines = self.log.len().max(log.len());
+        let line = |log: &[String], i: usize| {
+            log.get(i)
+                .cloned()
+                .unwrap_or_else(|| "(end of log)".to_string())
+        };
+        Ok((0..lines)
+            .find(|&i| self.log.get(i) != log.get(i))
+            .map(|i| (i + 1, line(&self.log, i), line(&log, i))))
+    }
+}
+
+impl fmt::Display for Golden {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "config {}", self.config.as_deref().unwrap_or("standard"))?;
+        writeln!(f, "seed {}", self.seed)?;
+        writeln!(f, "strategy {}", self.strategy)?;
+        writeln!(f)?;
+        for line in &self.log {
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Paths of the recorded games in `dir`, sorted.
+pub fn paths(dir: &str) -> Result<Vec<String>, String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("{}: {}", dir, e))?;
+    let mut paths = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|e| format!("{}: {}", dir, e))?.path();
+        if path.extension().is_some_and(|ext| ext == "log") {
+            paths.push(path.to_string_lossy().into_owned());
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Replays every recorded game in `dir` and describes those that now play
+/// out differently.
+pub fn check(dir: &str) -> Result<Vec<String>, String> {
+    let mut changed = Vec::new();
+    for path in paths(dir)? {
+        let golden = Golden::load(&path)?;
+        if let Some((line, was, now)) = golden
+            .first_difference()
+            .map_err(|e| format!("{}: {}", path, e))?
+        {
+            changed.push(format!(
+                "{} differs from log entry {}:\n  was {}\n  now {}",
+                path, line, was, now
+            ));
+        }
+    }
+    Ok(changed)
+}
@@ -1,8 +1,43 @@
+mod analysis;
+mod batch;
+mod campaign;
+mod cards;
+mod compare;
+mod config;
+mod golden;
+mod observation;
+mod puzzle;
+mod replay;
+mod report;
+mod series;
+mod strategy;
+
+use config::GameConfig;
+use observation::Observation;
 use rand::seq::SliceRandom;
-use rand::thread_rng;
-use std::collections::HashMap;
+use rand::{thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fmt;
+use std::process;
+use strategy::{Action, Seats, Strategy};
+
+/// Years in a full game, and the infrastructure hits that end it early.
+const YEARS_PER_GAME: usize = 9;
+const INFRA_DAMAGE_LIMIT: usize = 3;
+
+/// Prints game commentary unless the game is being played silently in a batch.
+macro_rules! say {
+    ($gs:expr, $($arg:tt)*) => {
+        if $gs.verbose {
+            println!($($arg)*);
+        }
+    };
+}
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
 enum DamageCard {
     FacilityDamage(RoleName),
     FacilityDestruction(RoleName),
@@ -11,129 +46,647 @@ enum DamageCard {
 
 type EventCardID = String;
 
-#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
 enum BonusCard {
     Cancel(EventCardID, Vec<RoleName>),
     Build(RoleName),
 }
 
-#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
 enum EventCard {
     Problem(
         EventCardID,
         Vec<(RoleName, RoleName)>,
-        Option<RoleName>, // override for damage
+        Option<DamageRule>,
+        Option<DamageCard>,
+    ),
+    /// A problem that stays in play for the given number of years unless it
+    /// is cancelled or paid for, drawing a fresh damage card each year.
+    Persistent(
+        EventCardID,
+        Vec<(RoleName, RoleName)>,
+        Option<DamageRule>,
         Option<DamageCard>,
+        usize,
     ),
+    /// Good fortune, taken as soon as the action phase starts.
+    Opportunity(EventCardID, Opportunity),
     NoProblem,
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+/// How a problem changes the damage card dealt to it when it fails.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+enum DamageRule {
+    /// Facility damage or destruction falls on this role instead of the
+    /// one on the damage card.
+    RedirectTo(RoleName),
+    /// Facility damage or destruction falls on the role with the fewest
+    /// facilities, the first by name if several are tied.
+    RedirectToWeakest,
+    /// Whatever the damage card, the colony takes infrastructure damage.
+    Infrastructure,
+    /// The damage card takes effect twice.
+    Double,
+}
+
+/// What an `EventCard::Opportunity` gives the colony.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+enum Opportunity {
+    /// Extra resources for the role this year.
+    Resources(RoleName, usize),
+    /// A facility for the role at no cost. The role can still act.
+    Build(RoleName),
+}
+
+impl EventCard {
+    /// Whether the card asks anything of the colony. Everything else is
+    /// logged but needs no resolution.
+    fn is_problem(&self) -> bool {
+        matches!(self, EventCard::Problem(..) | EventCard::Persistent(..))
+    }
+
+    fn id(&self) -> Option<&EventCardID> {
+        match self {
+            EventCard::Problem(id, ..)
+            | EventCard::Persistent(id, ..)
+            | EventCard::Opportunity(id, _) => Some(id),
+            EventCard::NoProblem => None,
+        }
+    }
+
+    /// Which role pays which role's resource to deal with a problem.
+    fn role_costs(&self) -> &[(RoleName, RoleName)] {
+        match self {
+            EventCard::Problem(_, role_costs, ..) | EventCard::Persistent(_, role_costs, ..) => {
+                role_costs
+            }
+            _ => &[],
+        }
+    }
+
+    /// The damage card dealt with a problem, once it is in play.
+    fn damage(&self) -> Option<&DamageCard> {
+        match self {
+            EventCard::Problem(_, _, _, damage) | EventCard::Persistent(_, _, _, damage, _) => {
+                damage.as_ref()
+            }
+            _ => None,
+        }
+    }
+
+    /// The card with `damage` attached in place of any it had. Only
+    /// problems take damage cards.
+    fn with_damage(self, damage: Option<DamageCard>) -> EventCard {
+        match self {
+            EventCard::Problem(id, role_costs, damage_rule, _) => {
+                EventCard::Problem(id, role_costs, damage_rule, damage)
+            }
+            EventCard::Persistent(id, role_costs, damage_rule, _, years) => {
+                EventCard::Persistent(id, role_costs, damage_rule, damage, years)
+            }
+            other => other,
+        }
+    }
+}
+
+/// One line of a game's history, tagged with the year it happened in.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+enum LogEntry {
+    Drawn(usize, EventCard),
+    Cancelled(usize, EventCardID, RoleName),
+    Resolved(usize, EventCardID),
+    Failed(usize, EventCardID, DamageCard),
+    Repaired(usize),
+    FacilityRepaired(usize, RoleName),
+    Traded(usize, RoleName, RoleName),
+    Opportunity(usize, EventCardID),
+    /// A role's ability put back a damage card for the next one.
+    Rerolled(usize, RoleName, DamageCard),
+    /// A failed event whose damage a role's ability discarded.
+    Shielded(usize, EventCardID, RoleName),
+    /// Damaged facilities that produced nothing in the good phase.
+    Idle(usize, RoleName, usize),
+    /// A bonus card given up to keep the role's hand within the limit.
+    Discarded(usize, RoleName, BonusCard),
+    /// A bonus card handed from the first role to the second.
+    Gave(usize, RoleName, RoleName, BonusCard),
+    /// A role built a facility with a bonus card.
+    BonusBuilt(usize, RoleName),
+    /// Damage a failed event did beyond its first card, from escalation or
+    /// its damage rule. Every card the event does is logged once, the first
+    /// as `Failed`.
+    ExtraDamage(usize, EventCardID, DamageCard),
+    Signalled(usize, RoleName, Signal),
+}
+
+impl LogEntry {
+    /// The year the entry happened in, counted from 0.
+    fn year(&self) -> usize {
+        match self {
+            LogEntry::Drawn(year, _)
+            | LogEntry::Cancelled(year, ..)
+            | LogEntry::Resolved(year, _)
+            | LogEntry::Failed(year, ..)
+            | LogEntry::Repaired(year)
+            | LogEntry::FacilityRepaired(year, _)
+            | LogEntry::Traded(year, ..)
+            | LogEntry::Opportunity(year, _)
+            | LogEntry::Rerolled(year, ..)
+            | LogEntry::Shielded(year, ..)
+            | LogEntry::Idle(year, ..)
+            | LogEntry::Discarded(year, ..)
+            | LogEntry::Gave(year, ..)
+            | LogEntry::BonusBuilt(year, _)
+            | LogEntry::ExtraDamage(year, ..)
+            | LogEntry::Signalled(year, ..) => *year,
+        }
+    }
+}
+
+/// Resources a role gives up in a trade, and what the other role receives.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+struct TradeRate {
+    give: usize,
+    receive: usize,
+}
+
+/// Switches for rule variants being tested. The default is the printed game.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+struct Rules {
+    /// Whether a drawn `NoProblem` event uses up one of the year's
+    /// `get_num_event_cards` draws, or another card is drawn in its place.
+    quiet_events_count_as_draws: bool,
+    /// Roles that each pay one resource to remove one infrastructure damage.
+    /// Empty means repairs are not allowed.
+    repair_cost: Vec<RoleName>,
+    /// Whether damaged facilities stay damaged from year to year until
+    /// repaired, instead of recovering at the start of the next year.
+    persistent_facility_damage: bool,
+    /// Exchange rate for trades between roles in the planning phase.
+    /// `None` means roles cannot trade.
+    trade_rate: Option<TradeRate>,
+    /// Most bonus cards one role can hold. A role dealt one more discards a
+    /// card of the strategy's choosing. `None` means no limit.
+    hand_limit: Option<usize>,
+    /// Which role each year's bonus card is dealt to.
+    bonus_deal: BonusDeal,
+    /// Whether a role can give a bonus card it cannot play to a role that
+    /// can, in the planning phase. The receiver needs room in its hand.
+    give_bonus_cards: bool,
+    /// Harsher damage for events that fail late in the game. All the
+    /// entries that apply to an event add up.
+    escalation: Vec<Escalation>,
+    /// How much the players can tell each other.
+    table_talk: TableTalk,
+}
+
+/// Whether the colony is played by one mind or by a player per role.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+enum TableTalk {
+    /// One mind sees every hand and makes every choice.
+    Open,
+    /// Each role makes its own choices, seeing only its own hand and the
+    /// public state. Once the events are dealt the roles send `Signal`s.
+    Signals,
+    /// As `Signals`, but nothing can be said at all.
+    Silent,
+}
+
+/// The little a role can tell the others when table talk is limited.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+enum Signal {
+    /// The role holds a card that cancels this event in play.
+    CanCancel(EventCardID),
+    /// The role cannot pay its share of the events in play.
+    Short,
+}
+
+/// Makes failed events do more damage from a given year on.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+struct Escalation {
+    /// First year it applies in, counted from 0.
+    from_year: usize,
+    /// Events it applies to, by name. Empty means every event.
+    #[serde(default)]
+    events: Vec<EventCardID>,
+    /// Damage cards drawn on top of the event's own.
+    #[serde(default)]
+    extra_damage_cards: usize,
+    /// Whether facility damage becomes facility destruction.
+    #[serde(default)]
+    destroy: bool,
+}
+
+/// Who receives the bonus card dealt in the good phase.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+enum BonusDeal {
+    /// The role that can play the card: the role a build card names, or
+    /// the last role named on a cancel card.
+    ToPlayer,
+    /// Each role in turn, in order of name, whatever the card.
+    InTurn,
+}
+
+impl Default for Rules {
+    fn default() -> Rules {
+        Rules {
+            quiet_events_count_as_draws: true,
+            repair_cost: Vec::new(),
+            persistent_facility_damage: false,
+            trade_rate: None,
+            hand_limit: None,
+            bonus_deal: BonusDeal::ToPlayer,
+            give_bonus_cards: true,
+            escalation: Vec::new(),
+            table_talk: TableTalk::Open,
+        }
+    }
+}
+
+/// A special power a role has on top of the shared rules.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+enum Ability {
+    /// Once a year, infrastructure damage from a failed event can be put
+    /// back for the next damage card instead.
+    RerollDamage,
+    /// One more resource in the good phase while the role has at least
+    /// this many working facilities.
+    ExtraResource(usize),
+    /// Failed events with this name do no damage.
+    IgnoreDamage(EventCardID),
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
 struct Role {
     facilities: usize,
     facilites_damaged: usize,
+    facility_cap: usize,
     name: RoleName,
+    resource: String,
     resources: usize,
     acted: bool,
+    abilities: Vec<Ability>,
+    /// Whether a once-a-year ability has been used this year.
+    ability_used: bool,
+    /// Bonus cards held by the role, oldest first.
+    hand: Vec<BonusCard>,
+    /// What each working facility produces, the first facility first.
+    production: Vec<usize>,
+    /// What every role pays to build this role's next facility, by the
+    /// number it has now: the first entry takes it from one to two.
+    build_cost: Vec<usize>,
+}
+
+impl Role {
+    /// Resources the working facilities produce in the good phase.
+    fn output(&self) -> usize {
+        let working = self.facilities.saturating_sub(self.facilites_damaged);
+        self.production.iter().take(working).sum()
+    }
+
+    /// What every role pays for this role's next build. A build at the cap
+    /// adds nothing but still costs one resource each.
+    fn next_build_cost(&self) -> usize {
+        self.build_cost
+            .get(self.facilities - 1)
+            .copied()
+            .unwrap_or(1)
+    }
+
+    /// What every role pays to repair one of this role's facilities: what
+    /// its newest facility cost to build, or one resource for the first.
+    fn repair_cost(&self) -> usize {
+        self.facilities
+            .checked_sub(2)
+            .and_then(|level| self.build_cost.get(level))
+            .copied()
+            .unwrap_or(1)
+    }
+
+    /// Whether the role can play `card` from its hand: a build card only for
+    /// itself, a cancel card only if the card names it.
+    fn can_play(&self, card: &BonusCard) -> bool {
+        match card {
+            BonusCard::Cancel(_, roles) => roles.contains(&self.name),
+            BonusCard::Build(role) => role == &self.name,
+        }
+    }
+
+    /// Whether the role holds a card that cancels `id` and may play it.
+    fn can_cancel(&self, id: &EventCardID) -> bool {
+        self.hand.iter().any(|card| {
+            matches!(card, BonusCard::Cancel(cancelled, _) if cancelled == id)
+                && self.can_play(card)
+        })
+    }
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
-enum RoleName {
-    Hab,
-    Joul,
-    Man,
+/// A colony department, as named in the config. Cards refer to roles by name.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+struct RoleName(String);
+
+impl From<&str> for RoleName {
+    fn from(name: &str) -> RoleName {
+        RoleName(name.to_string())
+    }
+}
+
+impl fmt::Debug for RoleName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
 }
 
-#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(Clone, Debug)]
 struct GameState {
-    bonus_cards_in_play: Vec<BonusCard>,
     event_deck: Vec<EventCard>,
     event_cards_in_play: Vec<EventCard>,
+    /// Persistent events that failed and come back next year.
+    persistent_events: Vec<EventCard>,
     bonus_deck: Vec<BonusCard>,
+    /// Bonus cards played or discarded.
+    bonus_discards: Vec<BonusCard>,
     damage_deck: Vec<DamageCard>,
+    /// Events that have left play, without their damage cards.
+    event_discards: Vec<EventCard>,
+    /// Damage cards turned over since the damage deck was last shuffled.
+    damage_discards: Vec<DamageCard>,
+    /// What the roles told each other this year.
+    signals: Vec<(RoleName, Signal)>,
     year_number: usize,
-    roles: HashMap<RoleName, Role>,
+    /// Kept in order of name, so that every pass over the roles, and the
+    /// log it writes, comes out the same from one run to the next.
+    roles: BTreeMap<RoleName, Role>,
     infra_damage: usize,
+    damage_cards: Vec<DamageCard>,
+    rules: Rules,
+    event_log: Vec<LogEntry>,
+    /// The colony at the end of each year played so far.
+    history: Vec<series::YearSnapshot>,
+    /// Shuffles every deck, so a seed fixes the order of all the cards.
+    rng: ChaCha20Rng,
+    verbose: bool,
 }
 
 impl GameState {
-
     fn reinit_damage(&mut self) {
-        let mut damage_deck = Vec::new();
-        damage_deck.push(DamageCard::InfrastructureDamage);
-        damage_deck.push(DamageCard::InfrastructureDamage);
-        damage_deck.push(DamageCard::InfrastructureDamage);
-        damage_deck.push(DamageCard::FacilityDamage(RoleName::Hab));
-        damage_deck.push(DamageCard::FacilityDamage(RoleName::Man));
-        damage_deck.push(DamageCard::FacilityDamage(RoleName::Joul));
-        damage_deck.push(DamageCard::FacilityDestruction(RoleName::Hab));
-        damage_deck.push(DamageCard::FacilityDestruction(RoleName::Man));
-        damage_deck.push(DamageCard::FacilityDestruction(RoleName::Joul));
-        damage_deck.shuffle(&mut thread_rng());
+        let mut damage_deck = self.damage_cards.clone();
+        damage_deck.shuffle(&mut self.rng);
         self.damage_deck = damage_deck;
+        self.damage_discards.clear();
     }
-    fn deal_bonus_card(&mut self) {
-        self.bonus_deck
-            .pop()
-            .map(|card| self.bonus_cards_in_play.push(card));
+
+    /// The game as the players see it.
+    fn observe(&self) -> Observation<'_> {
+        Observation::new(self)
     }
 
-    fn resources_left_to_build(&self) -> bool {
-        let mut resources_left = true;
-        for (_, role) in &self.roles {
-            println!("{} {:?}", role.resources, role.name);
-            resources_left = resources_left && (role.resources > 0);
+    /// The game as `role` sees it. With open table talk that is everything
+    /// the players see.
+    fn observe_as(&self, role: &RoleName) -> Observation<'_> {
+        match self.rules.table_talk {
+            TableTalk::Open => Observation::new(self),
+            TableTalk::Signals | TableTalk::Silent => Observation::for_role(self, role),
         }
-        resources_left
     }
 
-    fn deal_event_and_damage_cards(&mut self, num: usize) {
-        for x in 0..num {
-            println!("Dealing card {} of {:?} ", x, num);
-            self.event_deck.pop().map(|card| match card {
-                EventCard::Problem(cardid, roles, damage_override, _) => {
-                    if self.damage_deck.len() == 0 {
-                        self.reinit_damage();
+    /// The role whose choice `action` is.
+    fn actor(&self, action: &Action) -> Option<RoleName> {
+        match action {
+            Action::Build(role) | Action::RepairFacility(role) => Some(role.clone()),
+            Action::Trade(from, _) | Action::Give(from, ..) => Some(from.clone()),
+            Action::Repair => self.rules.repair_cost.first().cloned(),
+            Action::Pass => None,
+        }
+    }
+
+    /// Asks for the next action among `options`. With limited table talk
+    /// each role in turn picks from its own options with its own view, and
+    /// the first that does not pass acts.
+    fn decide(&self, strategy: &mut dyn Strategy, options: &[Action]) -> Action {
+        if self.rules.table_talk == TableTalk::Open {
+            return strategy.choose_action(&self.observe(), options);
+        }
+        let names: Vec<&RoleName> = self.roles.keys().collect();
+        for name in names {
+            let own: Vec<Action> = options
+                .iter()
+                .filter(|action| self.actor(action).as_ref() == Some(name))
+                .cloned()
+                .collect();
+            if own.is_empty() {
+                continue;
+            }
+            match strategy.choose_action(&self.observe_as(name), &own) {
+                Action::Pass => {}
+                action => return action,
+            }
+        }
+        Action::Pass
+    }
+
+    /// Each role says which events in play it can cancel and whether it is
+    /// short of resources, if table talk allows signals at all.
+    fn send_signals(&mut self) {
+        self.signals.clear();
+        if self.rules.table_talk != TableTalk::Signals {
+            return;
+        }
+        let names: Vec<RoleName> = self.roles.keys().cloned().collect();
+        for name in names {
+            let role = &self.roles[&name];
+            let mut signals = Vec::new();
+            let mut owed = 0;
+            for card in self.event_cards_in_play.iter().filter(|c| c.is_problem()) {
+                let id = card.id().unwrap();
+                let signal = Signal::CanCancel(id.clone());
+                if role.can_cancel(id) && !signals.contains(&signal) {
+                    signals.push(signal);
+                }
+                owed += card
+                    .role_costs()
+                    .iter()
+                    .filter(|(_, cost)| cost == &name)
+                    .count();
+            }
+            if role.resources < owed {
+                signals.push(Signal::Short);
+            }
+            for signal in signals {
+                say!(self, "{:?} signals {:?}", name, signal);
+                self.event_log.push(LogEntry::Signalled(
+                    self.year_number,
+                    name.clone(),
+                    signal.clone(),
+                ));
+                self.signals.push((name.clone(), signal));
+            }
+        }
+    }
+    /// Deals the top bonus card to the role the rules choose. If that takes
+    /// the role over the hand limit, the strategy picks a card to discard.
+    fn deal_bonus_card(&mut self, strategy: &mut dyn Strategy) {
+        let card = match self.bonus_deck.pop() {
+            Some(card) => card,
+            None => return,
+        };
+        let names: Vec<&RoleName> = self.roles.keys().collect();
+        let name = match (self.rules.bonus_deal, &card) {
+            (BonusDeal::ToPlayer, BonusCard::Cancel(_, roles)) if !roles.is_empty() => {
+                roles[roles.len() - 1].clone()
+            }
+            (BonusDeal::ToPlayer, BonusCard::Build(role)) => role.clone(),
+            _ => names[self.year_number % names.len()].clone(),
+        };
+        say!(self, "{:?} is dealt {:?}", name, card);
+        let role = self.roles.get_mut(&name).unwrap();
+        role.hand.push(card);
+        let hand = role.hand.clone();
+        if hand.len() > self.rules.hand_limit.unwrap_or(usize::MAX) {
+            let pos = strategy
+                .choose_discard(&self.observe_as(&name), &name, &hand)
+                .min(hand.len() - 1);
+            let card = self.roles.get_mut(&name).unwrap().hand.remove(pos);
+            say!(self, "{:?} discards {:?}", name, card);
+            self.bonus_discards.push(card.clone());
+            self.event_log
+                .push(LogEntry::Discarded(self.year_number, name, card));
+        }
+    }
+
+    /// Bonus cards held by all the roles together.
+    fn bonus_cards_held(&self) -> usize {
+        self.roles.values().map(|role| role.hand.len()).sum()
+    }
+
+    /// Every card a role could give away right now: one it cannot play, to
+    /// a role that can and has room for it.
+    fn give_options(&self) -> Vec<Action> {
+        if !self.rules.give_bonus_cards {
+            return Vec::new();
+        }
+        let limit = self.rules.hand_limit.unwrap_or(usize::MAX);
+        let names: Vec<&RoleName> = self.roles.keys().collect();
+        let mut options = Vec::new();
+        for from in &names {
+            let giver = &self.roles[*from];
+            for card in &giver.hand {
+                if giver.can_play(card) {
+                    continue;
+                }
+                for to in &names {
+                    let taker = &self.roles[*to];
+                    let option = Action::Give((*from).clone(), (*to).clone(), card.clone());
+                    if taker.can_play(card)
+                        && taker.hand.len() < limit
+                        && !options.contains(&option)
+                    {
+                        options.push(option);
                     }
-                    let damage = self.damage_deck.pop();
-                    damage.map(|damage_card| {
-                        println!("{}, {:?} ", cardid, damage_card);
-                        self.event_cards_in_play.push(EventCard::Problem(
-                            cardid,
-                            roles,
-                            damage_override,
-                            Some(damage_card),
-                        ))
-                    });
                 }
-                EventCard::NoProblem => {}
-            });
+            }
+        }
+        options
+    }
+
+    fn give(&mut self, from: RoleName, to: RoleName, card: BonusCard) {
+        if let Some(giver) = self.roles.get_mut(&from) {
+            take(&mut giver.hand, &card);
+        }
+        if let Some(taker) = self.roles.get_mut(&to) {
+            taker.hand.push(card.clone());
         }
+        self.event_log
+            .push(LogEntry::Gave(self.year_number, from, to, card));
+    }
+
+    fn deal_event_and_damage_cards(&mut self, num: usize) {
+        let mut dealt = 0;
+        while dealt < num {
+            let card = match self.event_deck.pop() {
+                Some(card) => card,
+                None => break,
+            };
+            say!(self, "Dealing card {} of {:?} ", dealt, num);
+            let card = self.deal_damage_card(card);
+            if self.counts_as_draw(&card) {
+                dealt += 1;
+            }
+            self.event_log
+                .push(LogEntry::Drawn(self.year_number, card.clone()));
+            self.event_cards_in_play.push(card);
+        }
+    }
+
+    /// Attaches the next damage card to a problem, reshuffling the damage
+    /// deck when it runs out.
+    fn deal_damage_card(&mut self, card: EventCard) -> EventCard {
+        if !card.is_problem() {
+            return card;
+        }
+        if self.damage_deck.is_empty() {
+            self.reinit_damage();
+        }
+        let damage = self.damage_deck.pop();
+        say!(self, "{:?}, {:?} ", card.id(), damage);
+        card.with_damage(damage)
+    }
+
+    /// Persistent events left over from last year come back into play with
+    /// new damage cards. They do not use up any of the year's draws.
+    fn return_persistent_events(&mut self) {
+        for card in std::mem::take(&mut self.persistent_events) {
+            say!(self, "Still in play: {:?}", card.id());
+            let card = self.deal_damage_card(card);
+            self.event_cards_in_play.push(card);
+        }
+    }
+
+    fn counts_as_draw(&self, card: &EventCard) -> bool {
+        card != &EventCard::NoProblem || self.rules.quiet_events_count_as_draws
+    }
+
+    /// How many of this year's `get_num_event_cards` draws have been made.
+    /// Returning persistent events are not draws.
+    fn events_dealt(&self) -> usize {
+        self.event_cards_in_play
+            .iter()
+            .filter(|card| self.counts_as_draw(card))
+            .count()
     }
 
     fn get_num_event_cards(&self) -> usize {
-        return (self.year_number / 3) + 2;
+        (self.year_number / 3) + 2
     }
     fn get_unacted_roles(&self) -> Vec<(RoleName, usize)> {
         let mut roles = Vec::new();
-        for (_role_name, role) in &self.roles {
+        for role in self.roles.values() {
             if !role.acted {
-                roles.push((role.name, role.facilities))
+                roles.push((role.name.clone(), role.facilities))
             }
         }
-        roles.shuffle(&mut thread_rng());
+        roles.sort();
         roles
     }
 
+    /// Whether every role can pay for a build by `role_name`.
+    fn can_build(&self, role_name: &RoleName) -> bool {
+        let cost = self.roles[role_name].next_build_cost();
+        self.roles.values().all(|role| role.resources >= cost)
+    }
+
     fn build(&mut self, role_to_build: RoleName, with_card: bool) {
+        let cost = self.roles[&role_to_build].next_build_cost();
         for (role_name, role) in self.roles.iter_mut() {
             //Remove resources if a card is not being used
             if !with_card {
-                role.resources = role.resources - 1;
+                role.resources -= cost;
             }
             if role_name == &role_to_build {
-                if role.facilities < 4 {
-                    role.facilities = role.facilities + 1;
+                if role.facilities < role.facility_cap {
+                    role.facilities += 1;
                 }
                 //Not strictly true but worth pointing out
                 role.acted = true;
@@ -141,540 +694,1994 @@ impl GameState {
         }
     }
 
-    fn do_damage_card(&mut self, override_role: Option<RoleName>, damage_card: DamageCard) {
+    fn can_repair(&self) -> bool {
+        if self.rules.repair_cost.is_empty() || self.infra_damage == 0 || self.collapsed() {
+            return false;
+        }
+        self.rules.repair_cost.iter().all(|payer| {
+            let owed = self
+                .rules
+                .repair_cost
+                .iter()
+                .filter(|r| *r == payer)
+                .count();
+            self.roles
+                .get(payer)
+                .is_some_and(|role| role.resources >= owed)
+        })
+    }
+
+    /// Repairing a facility costs every role the role's `repair_cost`.
+    fn can_repair_facility(&self, role_name: &RoleName) -> bool {
+        let role = match self.roles.get(role_name) {
+            Some(role) => role,
+            None => return false,
+        };
+        let cost = role.repair_cost();
+        self.rules.persistent_facility_damage
+            && !role.acted
+            && role.facilites_damaged > 0
+            && self.roles.values().all(|role| role.resources >= cost)
+    }
+
+    fn repair_facility(&mut self, role_to_repair: RoleName) {
+        let cost = self.roles[&role_to_repair].repair_cost();
+        for (role_name, role) in self.roles.iter_mut() {
+            role.resources -= cost;
+            if role_name == &role_to_repair {
+                role.facilites_damaged -= 1;
+                role.acted = true;
+            }
+        }
+        self.event_log
+            .push(LogEntry::FacilityRepaired(self.year_number, role_to_repair));
+    }
+
+    fn repair(&mut self) {
+        for payer in &self.rules.repair_cost {
+            if let Some(role) = self.roles.get_mut(payer) {
+                role.resources -= 1;
+            }
+        }
+        self.infra_damage -= 1;
+        self.event_log.push(LogEntry::Repaired(self.year_number));
+    }
+
+    /// Every trade the exchange rate allows right now.
+    fn trade_options(&self) -> Vec<Action> {
+        let rate = match self.rules.trade_rate {
+            Some(rate) => rate,
+            None => return Vec::new(),
+        };
+        let names: Vec<&RoleName> = self.roles.keys().collect();
+        let mut options = Vec::new();
+        for from in &names {
+            if self.roles[*from].resources < rate.give {
+                continue;
+            }
+            for to in &names {
+                if from != to {
+                    options.push(Action::Trade((*from).clone(), (*to).clone()));
+                }
+            }
+        }
+        options
+    }
+
+    fn trade(&mut self, from: RoleName, to: RoleName) {
+        if let Some(rate) = self.rules.trade_rate {
+            if let Some(role) = self.roles.get_mut(&from) {
+                role.resources -= rate.give;
+            }
+            if let Some(role) = self.roles.get_mut(&to) {
+                role.resources += rate.receive;
+            }
+            self.event_log
+                .push(LogEntry::Traded(self.year_number, from, to));
+        }
+    }
+
+    /// Everything the colony could spend its remaining resources on.
+    fn build_options(&self) -> Vec<Action> {
+        let mut options = Vec::new();
+        for (role, _) in self.get_unacted_roles() {
+            if self.can_repair_facility(&role) {
+                options.push(Action::RepairFacility(role.clone()));
+            }
+            if self.can_build(&role) {
+                options.push(Action::Build(role));
+            }
+        }
+        if self.can_repair() {
+            options.push(Action::Repair);
+        }
+        options
+    }
+
+    fn collapsed(&self) -> bool {
+        self.infra_damage >= INFRA_DAMAGE_LIMIT
+    }
+
+    /// The damage `damage_card` does once `rule` has changed it, one card
+    /// per time it takes effect.
+    fn apply_rule(&self, rule: &Option<DamageRule>, damage_card: DamageCard) -> Vec<DamageCard> {
+        let damage_card = match (rule, damage_card) {
+            (Some(DamageRule::Infrastructure), _) => DamageCard::InfrastructureDamage,
+            (Some(DamageRule::Double), damage_card) => {
+                return vec![damage_card.clone(), damage_card];
+            }
+            (Some(DamageRule::RedirectTo(role)), DamageCard::FacilityDamage(_)) => {
+                DamageCard::FacilityDamage(role.clone())
+            }
+            (Some(DamageRule::RedirectTo(role)), DamageCard::FacilityDestruction(_)) => {
+                DamageCard::FacilityDestruction(role.clone())
+            }
+            (Some(DamageRule::RedirectToWeakest), DamageCard::FacilityDamage(_)) => {
+                DamageCard::FacilityDamage(self.weakest_role())
+            }
+            (Some(DamageRule::RedirectToWeakest), DamageCard::FacilityDestruction(_)) => {
+                DamageCard::FacilityDestruction(self.weakest_role())
+            }
+            (_, damage_card) => damage_card,
+        };
+        vec![damage_card]
+    }
+
+    fn do_damage_card(&mut self, damage_card: DamageCard) {
         match damage_card {
-            DamageCard::InfrastructureDamage => self.infra_damage = self.infra_damage + 1,
+            DamageCard::InfrastructureDamage => self.infra_damage += 1,
             DamageCard::FacilityDamage(card_role) => {
-                let damage_type = match override_role {
-                    Some(override_rolename) => override_rolename,
-                    None => card_role,
-                };
-                let opt_role = self.roles.get_mut(&damage_type);
-                opt_role.map(|role| {
-                    role.facilites_damaged = role.facilites_damaged + 1;
-                });
+                let opt_role = self.roles.get_mut(&card_role);
+                if let Some(role) = opt_role {
+                    role.facilites_damaged = (role.facilites_damaged + 1).min(role.facilities);
+                }
             }
             DamageCard::FacilityDestruction(card_role) => {
-                let damage_type = match override_role {
-                    Some(override_rolename) => override_rolename,
-                    None => card_role,
-                };
-                let opt_role = self.roles.get_mut(&damage_type);
-                opt_role.map(|role| {
+                let opt_role = self.roles.get_mut(&card_role);
+                if let Some(role) = opt_role {
                     if role.facilities > 1 {
-                        role.facilities = role.facilities - 1;
+                        role.facilities -= 1;
                     } else if role.facilities == 1 {
-                        role.facilites_damaged = role.facilites_damaged + 1;
+                        role.facilites_damaged += 1;
                     }
-                });
+                    role.facilites_damaged = role.facilites_damaged.min(role.facilities);
+                }
             }
         }
     }
 
+    /// The role with the fewest facilities, the first by name on a tie.
+    fn weakest_role(&self) -> RoleName {
+        self.roles
+            .values()
+            .map(|role| (role.facilities, role.name.clone()))
+            .min()
+            .map(|(_, name)| name)
+            .expect("a game has roles")
+    }
+
     fn deal_with_event(&mut self, event_card: EventCard) {
         //spend all resources for event
-        match event_card {
-            EventCard::Problem(_role, role_costs, _, _) => {
-                for (role, cost) in role_costs {
-                    self.roles.get_mut(&cost).map(|the_role_cost| {
-                        the_role_cost.resources = the_role_cost.resources - 1;
-                    });
-                    self.roles.get_mut(&role).map(|the_role_acted| {
-                        the_role_acted.acted = true;
-                    });
-                }
+        for (role, cost) in event_card.role_costs() {
+            if let Some(the_role_cost) = self.roles.get_mut(cost) {
+                the_role_cost.resources -= 1;
+            }
+            if let Some(the_role_acted) = self.roles.get_mut(role) {
+                the_role_acted.acted = true;
             }
-            _ => {}
         }
 
         //set roleName acted
     }
 
-    fn can_deal_with_event(&mut self, event_card: &EventCard) -> bool {
-        let mut can_deal = true;
-        match event_card {
-            EventCard::Problem(_, role_costs, _, _) => {
-                for (_, cost) in role_costs {
-                    self.roles
-                        .get(&cost)
-                        .map(|the_role| can_deal = can_deal && (the_role.resources > 0));
-                }
-            }
-            _ => {}
-        }
-        can_deal
+    /// Whether the roles can pay for the event. A role named as paying more
+    /// than once pays a resource each time.
+    fn can_deal_with_event(&self, event_card: &EventCard) -> bool {
+        let costs = event_card.role_costs();
+        costs.iter().all(|(_, payer)| {
+            let owed = costs.iter().filter(|(_, cost)| cost == payer).count();
+            self.roles
+                .get(payer)
+                .is_none_or(|role| role.resources >= owed)
+        })
     }
-    fn spend_cancel_card(&mut self, role: RoleName, pos: usize) {
-        //remove bonus card
-        self.bonus_cards_in_play.remove(pos);
-        self.roles.get_mut(&role).map(|role| {
+    /// Plays the card at `pos` in the role's hand to cancel an event. The
+    /// role acts.
+    fn spend_cancel_card(&mut self, name: &RoleName, pos: usize) {
+        if let Some(role) = self.roles.get_mut(name) {
+            let card = role.hand.remove(pos);
             role.acted = true;
-        });
+            self.bonus_discards.push(card);
+        }
+    }
 
-        //set roleName acted
+    /// The first role, by name, holding a card that cancels `event_id` and
+    /// able to play it, with the card's place in its hand. Only looks, so
+    /// strategies and the solver can ask without the game saying anything.
+    fn find_cancel(&self, event_id: &EventCardID) -> Option<(RoleName, usize)> {
+        self.roles.iter().find_map(|(name, role)| {
+            role.hand
+                .iter()
+                .position(|card| {
+                    matches!(card, BonusCard::Cancel(id, _) if id == event_id)
+                        && role.can_play(card)
+                })
+                .map(|pos| (name.clone(), pos))
+        })
     }
-    fn find_roles_with_cancel(&self, event_id: &EventCardID) -> (Vec<RoleName>, usize) {
-        let mut roles = Vec::new();
-        let mut pos = 0;
-        let mut found_pos = 0;
-        for bonus_card in &self.bonus_cards_in_play {
-            match bonus_card {
-                BonusCard::Cancel(id, cancel_roles) => {
-                    if id == event_id {
-                        roles = cancel_roles.clone();
-                        println!("Found card {}", id)
-                    }
-                    found_pos = pos
-               }
-                _ => {}
-            };
-            pos = pos + 1;
-        }
-        (roles, found_pos)
-    }
-    fn find_role_with_build(&self, role: RoleName) -> Option<usize> {
-        let mut pos = 0;
-        let mut found_pos = 0;
-        let mut found = false;
-        for bonus_card in &self.bonus_cards_in_play {
-            match bonus_card {
-                BonusCard::Build(build_role) => {
-                    if role == *build_role {
-                        println!("Found card {:?}", build_role);
-                        found = true;
-                        found_pos = pos;
-                    }
-                }
-                _ => {}
-            };
-            pos = pos + 1;
-        }
-        if found {
-            Some(found_pos)
-        } else {
-            None
-        }
+
+    fn find_role_with_build(&self, role: &RoleName) -> Option<usize> {
+        self.roles
+            .get(role)?
+            .hand
+            .iter()
+            .position(|card| matches!(card, BonusCard::Build(build_role) if build_role == role))
     }
-    fn build_using_bonus(&mut self, role: RoleName) {
-        match self.find_role_with_build(role) {
-            Some(loc) => {
-                println!("Found building bonus card at {}", loc);
-                self.build(role, true);
-                self.bonus_cards_in_play.remove(loc);
+
+    /// Builds with the role's build card if it has one and the strategy
+    /// does not hold it for a later year.
+    fn build_using_bonus(&mut self, role: RoleName, strategy: &mut dyn Strategy) {
+        if let Some(loc) = self.find_role_with_build(&role) {
+            if !strategy.play_build(&self.observe_as(&role), &role) {
+                say!(self, "{:?} holds its building bonus card", role);
+                return;
             }
-            _ => {}
+            say!(self, "Found building bonus card at {}", loc);
+            let card = self.roles.get_mut(&role).unwrap().hand.remove(loc);
+            self.bonus_discards.push(card);
+            self.event_log
+                .push(LogEntry::BonusBuilt(self.year_number, role.clone()));
+            self.build(role, true);
         }
     }
-    fn play_year(&mut self) {
-        println!("Starting Good Phase");
-        // Good Stuff
-        self.deal_bonus_card();
-        for (_name, role) in self.roles.iter_mut() {
-            if role.facilities > role.facilites_damaged {
-                role.resources = role.facilities - role.facilites_damaged;
-            } else {
-                role.resources = 0;
+    /// Good phase production from each role's working facilities.
+    fn produce(&mut self) {
+        for (name, role) in self.roles.iter_mut() {
+            role.resources = role.output();
+            let working = role.facilities.saturating_sub(role.facilites_damaged);
+            for ability in &role.abilities {
+                if let Ability::ExtraResource(level) = ability {
+                    if working >= *level {
+                        role.resources += 1;
+                    }
+                }
+            }
+            if role.facilites_damaged > 0 {
+                self.event_log.push(LogEntry::Idle(
+                    self.year_number,
+                    name.clone(),
+                    role.facilites_damaged,
+                ));
+            }
+            if !self.rules.persistent_facility_damage {
+                role.facilites_damaged = 0;
             }
-            role.facilites_damaged = 0;
             role.acted = false;
+            role.ability_used = false;
         }
+    }
+
+    fn play_year(&mut self, strategy: &mut dyn Strategy) {
+        self.good_phase(strategy);
+        self.event_phase();
+        say!(self, "{}", self.observe());
+        self.resolve_year(strategy);
+        self.history.push(series::YearSnapshot::take(self));
+    }
+
+    fn good_phase(&mut self, strategy: &mut dyn Strategy) {
+        say!(self, "Starting Good Phase");
+        // Good Stuff
+        self.deal_bonus_card(strategy);
+        self.produce();
+    }
 
-        println!("Starting Event Phase");
+    fn event_phase(&mut self) {
+        say!(self, "Starting Event Phase");
 
         // Event + Planning
         let num_event_cards_to_play = self.get_num_event_cards();
-        
-        println!("NumEventCards = {}", num_event_cards_to_play);
-        self.deal_event_and_damage_cards(num_event_cards_to_play);
 
-        // Action
-        //Deal with Events
-        println!("Starting Action Phase");
+        say!(self, "NumEventCards = {}", num_event_cards_to_play);
+        self.deal_event_and_damage_cards(num_event_cards_to_play);
+        self.return_persistent_events();
+    }
 
-        // Deal with event cards
+    /// Cancels `card` with a bonus card if one matches and the strategy
+    /// plays it, pays for it if the roles can afford it, and otherwise takes
+    /// its damage. A persistent event that fails stays for next year if it
+    /// has years left.
+    fn resolve_event(&mut self, card: EventCard, strategy: &mut dyn Strategy) {
+        let card_clone = card.clone();
+        let (id, damage_rule, damage) = match card {
+            EventCard::Problem(id, _, damage_rule, Some(damage))
+            | EventCard::Persistent(id, _, damage_rule, Some(damage), _) => {
+                (id, damage_rule, damage)
+            }
+            EventCard::Opportunity(id, opportunity) => {
+                self.take_opportunity(id, opportunity);
+                self.event_discards.push(card_clone);
+                return;
+            }
+            _ => {
+                self.event_discards.push(card_clone);
+                return;
+            }
+        };
         // For event cards / check whether any bonus cards exist to remove
-        let cards_in_play = self.event_cards_in_play.clone();
-        for card in cards_in_play {
-            let card_clone = card.clone();
-            match card {
-                EventCard::Problem(id, _roles_effected, damage_override, opt_damage) => {
-                    match opt_damage {
-                        Some(damage) => {
-                            let (mut capable_roles, pos) = self.find_roles_with_cancel(&id);
-                            if capable_roles.len() > 0 {
-                                capable_roles.pop().map(|role| {
-                                    println!("Playing card to deal with {:?}", card_clone);
-                                    self.spend_cancel_card(role, pos);
-                                });
-                            } else if self.can_deal_with_event(&card_clone) {
-                                println!("Spending resources to deal with {:?}", card_clone);
-                                self.deal_with_event(card_clone);
-                            } else {
-                                println!("Failed to deal with {:?}", card_clone);
-                                self.do_damage_card(damage_override, damage);
-                            }
-                        }
-                        None => {}
-                    }
+        let face = card_clone.clone().with_damage(None);
+        let cancel = self
+            .find_cancel(&id)
+            .filter(|(role, _)| strategy.play_cancel(&self.observe_as(role), role, &face));
+        if let Some((role, pos)) = cancel {
+            say!(self, "Found card {} held by {:?}", id, role);
+            say!(self, "Playing card to deal with {:?}", card_clone);
+            self.spend_cancel_card(&role, pos);
+            self.event_log
+                .push(LogEntry::Cancelled(self.year_number, id, role));
+            self.event_discards.push(face);
+        } else if self.can_deal_with_event(&card_clone) {
+            say!(self, "Spending resources to deal with {:?}", card_clone);
+            self.deal_with_event(card_clone);
+            self.event_log
+                .push(LogEntry::Resolved(self.year_number, id));
+            self.event_discards.push(face);
+        } else {
+            say!(self, "Failed to deal with {:?}", card_clone);
+            self.take_damage(id, damage_rule, damage);
+            match card_clone {
+                EventCard::Persistent(id, role_costs, damage_rule, _, years) if years > 1 => {
+                    self.persistent_events.push(EventCard::Persistent(
+                        id,
+                        role_costs,
+                        damage_rule,
+                        None,
+                        years - 1,
+                    ))
                 }
-                EventCard::NoProblem => {}
+                _ => self.event_discards.push(face),
             }
         }
-        self.event_cards_in_play = Vec::new();
+    }
 
-        while self.resources_left_to_build() {
-            //Build something if you can
-            let mut roles_left_to_play = self.get_unacted_roles();
-            if roles_left_to_play.len() == 0 {
-                break;
-            }
-            let first_role = roles_left_to_play.pop();
-            first_role.map(|(role, _)| {
-                self.build(role, false);
+    /// Deals the damage of a failed event, unless a role's ability
+    /// discards or rerolls it.
+    fn take_damage(
+        &mut self,
+        id: EventCardID,
+        damage_rule: Option<DamageRule>,
+        damage: DamageCard,
+    ) {
+        self.damage_discards.push(damage.clone());
+        let ignore = Ability::IgnoreDamage(id.clone());
+        if let Some(role) = self.role_with(|role| role.abilities.contains(&ignore)) {
+            say!(self, "{:?} discards the damage from {}", role, id);
+            self.event_log
+                .push(LogEntry::Shielded(self.year_number, id, role));
+            return;
+        }
+        let mut damage = damage;
+        if damage == DamageCard::InfrastructureDamage {
+            let reroller = self.role_with(|role| {
+                role.abilities.contains(&Ability::RerollDamage) && !role.ability_used
             });
-
-            println!("Building!")
+            if let Some(name) = reroller {
+                if self.damage_deck.is_empty() {
+                    self.reinit_damage();
+                }
+                if let Some(next) = self.damage_deck.pop() {
+                    say!(self, "{:?} rerolls {:?} into {:?}", name, damage, next);
+                    self.damage_discards.push(next.clone());
+                    self.event_log
+                        .push(LogEntry::Rerolled(self.year_number, name.clone(), damage));
+                    self.roles.get_mut(&name).unwrap().ability_used = true;
+                    damage = next;
+                }
+            }
         }
-        let roles_left_to_play  = self.get_unacted_roles();
-        for (role, _) in roles_left_to_play {
-            self.build_using_bonus(role);
+        let mut drawn = vec![damage];
+        let (extra, destroy) = self.escalation(&id);
+        for _ in 0..extra {
+            if self.damage_deck.is_empty() {
+                self.reinit_damage();
+            }
+            if let Some(card) = self.damage_deck.pop() {
+                self.damage_discards.push(card.clone());
+                drawn.push(card);
+            }
+        }
+        let mut dealt = Vec::new();
+        for damage in drawn {
+            let damage = match damage {
+                DamageCard::FacilityDamage(role) if destroy => {
+                    DamageCard::FacilityDestruction(role)
+                }
+                damage => damage,
+            };
+            dealt.extend(self.apply_rule(&damage_rule, damage));
+        }
+        for (i, damage) in dealt.into_iter().enumerate() {
+            self.do_damage_card(damage.clone());
+            let entry = if i == 0 {
+                LogEntry::Failed(self.year_number, id.clone(), damage)
+            } else {
+                LogEntry::ExtraDamage(self.year_number, id.clone(), damage)
+            };
+            self.event_log.push(entry);
         }
+    }
 
-        // checkBonusCardForBuild
-        self.year_number = self.year_number + 1
-        // Cleanup
-        //Not needed as good stuff over writes.
-        //make sure no events in play
+    /// Extra damage cards a failed `id` draws this year, and whether its
+    /// facility damage becomes destruction, under the escalation rules.
+    fn escalation(&self, id: &EventCardID) -> (usize, bool) {
+        self.rules
+            .escalation
+            .iter()
+            .filter(|e| self.year_number >= e.from_year)
+            .filter(|e| e.events.is_empty() || e.events.contains(id))
+            .fold((0, false), |(extra, destroy), e| {
+                (extra + e.extra_damage_cards, destroy || e.destroy)
+            })
     }
 
-    fn new() -> GameState {
-        let mut event_deck = Vec::new();
+    /// The first role by name that `pred` holds for.
+    fn role_with(&self, pred: impl Fn(&Role) -> bool) -> Option<RoleName> {
+        self.roles
+            .values()
+            .filter(|role| pred(role))
+            .map(|role| role.name.clone())
+            .min()
+    }
 
-        //Spacecold issue, hab cost
-        event_deck.push(EventCard::Problem(
-            "Spacecold".to_string(),
-            vec![(RoleName::Hab, RoleName::Hab)],
-            Some(RoleName::Hab),
-            None,
-        ));
-        //Raiding issue, hab cost
-        event_deck.push(EventCard::Problem(
-            "Spacecold".to_string(),
-            vec![(RoleName::Hab, RoleName::Man)],
-            Some(RoleName::Hab),
-            None,
-        ));
-        //Raiding issue, man cost
-        event_deck.push(EventCard::Problem(
-            "Spacecold".to_string(),
-            vec![(RoleName::Hab, RoleName::Joul)],
-            Some(RoleName::Hab),
-            None,
-        ));
+    fn take_opportunity(&mut self, id: EventCardID, opportunity: Opportunity) {
+        say!(self, "Opportunity! {:?}", opportunity);
+        match opportunity {
+            Opportunity::Resources(name, amount) => {
+                if let Some(role) = self.roles.get_mut(&name) {
+                    role.resources += amount;
+                }
+            }
+            Opportunity::Build(name) => {
+                if let Some(role) = self.roles.get_mut(&name) {
+                    role.facilities = (role.facilities + 1).min(role.facility_cap);
+                }
+            }
+        }
+        self.event_log
+            .push(LogEntry::Opportunity(self.year_number, id));
+    }
 
-        //Mutiny issue, hab cost
-        event_deck.push(EventCard::Problem(
-            "Mutiny".to_string(),
-            vec![(RoleName::Hab, RoleName::Hab)],
-            None,
-            None,
-        ));
-        //Nanofab issue, hab cost
-        event_deck.push(EventCard::Problem(
-            "Mutiny".to_string(),
-            vec![(RoleName::Hab, RoleName::Hab)],
-            None,
-            None,
-        ));
-        //Nanofab issue, man cost
-        event_deck.push(EventCard::Problem(
-            "Mutiny".to_string(),
-            vec![(RoleName::Hab, RoleName::Hab)],
-            None,
-            None,
-        ));
+    /// Carries out one of `build_options`. Passing and trading do nothing here.
+    fn take_build_action(&mut self, action: Action) {
+        match action {
+            Action::Build(role) => {
+                self.build(role, false);
+                say!(self, "Building!")
+            }
+            Action::RepairFacility(role) => {
+                say!(self, "Repairing a {:?} facility!", role);
+                self.repair_facility(role)
+            }
+            Action::Repair => {
+                self.repair();
+                say!(
+                    self,
+                    "Repairing! Infrastructure damage now {}",
+                    self.infra_damage
+                )
+            }
+            Action::Pass | Action::Trade(..) | Action::Give(..) => {}
+        }
+    }
 
-        //Nanofab issue, energy cost
-        event_deck.push(EventCard::Problem(
-            "Nanobug".to_string(),
-            vec![(RoleName::Man, RoleName::Joul)],
-            None,
-            None,
-        ));
-        //Nanofab issue, hab cost
-        event_deck.push(EventCard::Problem(
-            "Nanobug".to_string(),
-            vec![(RoleName::Man, RoleName::Hab)],
-            None,
-            None,
-        ));
-        //Nanofab issue, man cost
-        event_deck.push(EventCard::Problem(
-            "Nanobug".to_string(),
-            vec![(RoleName::Man, RoleName::Man)],
-            None,
-            None,
-        ));
-        //Hab issue, energy cost
-        event_deck.push(EventCard::Problem(
-            "Raiding".to_string(),
-            vec![(RoleName::Hab, RoleName::Joul)],
-            None,
-            None,
-        ));
-        //Hab issue, hab cost
-        event_deck.push(EventCard::Problem(
-            "Raiding".to_string(),
-            vec![(RoleName::Hab, RoleName::Hab)],
-            None,
-            None,
-        ));
-        //Hab issue, man cost
-        event_deck.push(EventCard::Problem(
-            "Raiding".to_string(),
-            vec![(RoleName::Hab, RoleName::Man)],
-            None,
-            None,
-        ));
+    /// Roles that have not acted this year build with their bonus cards,
+    /// unless the strategy holds them.
+    fn build_with_bonus_cards(&mut self, strategy: &mut dyn Strategy) {
+        let roles_left_to_play = self.get_unacted_roles();
+        for (role, _) in roles_left_to_play {
+            self.build_using_bonus(role, strategy);
+        }
+    }
 
-        //Joul issue, energy cost, Joul Override
-        event_deck.push(EventCard::Problem(
-            "Surge".to_string(),
-            vec![(RoleName::Joul, RoleName::Joul)],
-            Some(RoleName::Joul),
-            None,
-        ));
-        //Joul issue, hab cost, Joul
-        event_deck.push(EventCard::Problem(
-            "Surge".to_string(),
-            vec![(RoleName::Joul, RoleName::Hab)],
-            Some(RoleName::Joul),
-            None,
-        ));
-        //Nanofab issue, man cost, Joul override
-        event_deck.push(EventCard::Problem(
-            "Surge".to_string(),
-            vec![(RoleName::Joul, RoleName::Man)],
-            Some(RoleName::Joul),
-            None,
-        ));
-        //Joul/Man issue, man/hab cost - meteor
-        event_deck.push(EventCard::Problem(
-            "Meteor".to_string(),
-            vec![
-                (RoleName::Joul, RoleName::Man),
-                (RoleName::Man, RoleName::Hab),
-            ],
-            None,
-            None,
-        ));
-        //Joul/Man issue, man/hab cost - meteor
-        event_deck.push(EventCard::Problem(
-            "Meteor".to_string(),
-            vec![
-                (RoleName::Joul, RoleName::Hab),
-                (RoleName::Man, RoleName::Joul),
-            ],
-            None,
-            None,
-        ));
-        //Joul/Man issue, man/hab cost - meteor
-        event_deck.push(EventCard::Problem(
-            "Meteor".to_string(),
-            vec![
-                (RoleName::Joul, RoleName::Joul),
-                (RoleName::Man, RoleName::Man),
-            ],
-            None,
-            None,
-        ));
-        //Joul/Man issue, man/hab cost - quake
-        event_deck.push(EventCard::Problem(
-            "Quake".to_string(),
-            vec![
-                (RoleName::Joul, RoleName::Man),
-                (RoleName::Man, RoleName::Hab),
-            ],
-            None,
-            None,
-        ));
-        //Joul/Man issue, man/hab cost - quake
-        event_deck.push(EventCard::Problem(
-            "Quake".to_string(),
-            vec![
-                (RoleName::Joul, RoleName::Hab),
-                (RoleName::Man, RoleName::Joul),
-            ],
-            None,
-            None,
-        ));
-        //Joul/Man issue, man/hab cost - quake
-        event_deck.push(EventCard::Problem(
-            "Quake".to_string(),
-            vec![
-                (RoleName::Joul, RoleName::Joul),
-                (RoleName::Man, RoleName::Man),
-            ],
-            None,
+    /// Everything after the cards are dealt: planning, action and building.
+    /// No cards are drawn from here on, so the year's outcome depends only on
+    /// the dealt cards and the strategy.
+    fn resolve_year(&mut self, strategy: &mut dyn Strategy) {
+        // Planning: roles may trade resources and give away bonus cards
+        // before facing the events
+        self.send_signals();
+        loop {
+            let mut options = self.trade_options();
+            options.extend(self.give_options());
+            if options.is_empty() {
+                break;
+            }
+            match self.decide(strategy, &options) {
+                Action::Trade(from, to) => {
+                    say!(self, "Trading from {:?} to {:?}", from, to);
+                    self.trade(from, to)
+                }
+                Action::Give(from, to, card) => {
+                    say!(self, "{:?} gives {:?} to {:?}", from, card, to);
+                    self.give(from, to, card)
+                }
+                _ => break,
+            }
+        }
+
+        // Action
+        //Deal with Events
+        say!(self, "Starting Action Phase");
+
+        // Deal with event cards, taking any opportunities first
+        let (opportunities, cards_in_play): (Vec<EventCard>, Vec<EventCard>) = self
+            .event_cards_in_play
+            .iter()
+            .cloned()
+            .partition(|card| matches!(card, EventCard::Opportunity(..)));
+        for card in opportunities.into_iter().chain(cards_in_play) {
+            self.resolve_event(card, strategy);
+        }
+        self.event_cards_in_play = Vec::new();
+
+        loop {
+            //Build or repair something if you can
+            let options = self.build_options();
+            if options.is_empty() {
+                break;
+            }
+            match self.decide(strategy, &options) {
+                Action::Pass | Action::Trade(..) | Action::Give(..) => break,
+                action => self.take_build_action(action),
+            }
+        }
+        self.build_with_bonus_cards(strategy);
+
+        // checkBonusCardForBuild
+        self.year_number += 1;
+        // Cleanup
+        //Not needed as good stuff over writes.
+        //make sure no events in play
+    }
+
+    /// Plays until the year limit or the colony collapses.
+    fn play_game(&mut self, strategy: &mut dyn Strategy) {
+        self.play_years(strategy, YEARS_PER_GAME - self.year_number);
+    }
+
+    fn play_years(&mut self, strategy: &mut dyn Strategy, years: usize) {
+        let mut seats = self.seats(strategy);
+        for _n in 0..years {
+            if self.collapsed() {
+                say!(self, "Kerblooey!");
+                break;
+            }
+            self.play_year(&mut seats);
+        }
+    }
+
+    /// Who plays the game for `strategy`: the strategy itself when every
+    /// hand is in view, otherwise a fresh player per role, so that no role
+    /// learns what another's player saw.
+    fn seats<'s>(&self, strategy: &'s mut dyn Strategy) -> Seats<'s> {
+        let roles: Vec<RoleName> = match self.rules.table_talk {
+            TableTalk::Open => Vec::new(),
+            TableTalk::Signals | TableTalk::Silent => self.roles.keys().cloned().collect(),
+        };
+        Seats::new(strategy, &roles)
+    }
+
+    fn from_config(config: &GameConfig) -> GameState {
+        GameState::seeded(config, thread_rng().gen())
+    }
+
+    /// A new game whose card order is fixed by `seed`. Each deck is shuffled
+    /// from its own stream, so changing one deck leaves the others' order
+    /// alone when two configurations are played with the same seed.
+    fn seeded(config: &GameConfig, seed: u64) -> GameState {
+        let mut seeds = ChaCha20Rng::seed_from_u64(seed);
+        let mut event_deck = config.event_deck.clone();
+        event_deck.shuffle(&mut ChaCha20Rng::seed_from_u64(seeds.gen()));
+        let mut bonus_deck = config.bonus_deck.clone();
+        bonus_deck.shuffle(&mut ChaCha20Rng::seed_from_u64(seeds.gen()));
+        let mut rng = ChaCha20Rng::seed_from_u64(seeds.gen());
+        let mut damage_deck = config.damage_deck.clone();
+        damage_deck.shuffle(&mut rng);
+
+        let scenario = &config.scenario;
+        let mut hands: HashMap<&RoleName, Vec<BonusCard>> = HashMap::new();
+        for (name, cards) in &scenario.hands {
+            for card in cards {
+                hands
+                    .entry(name)
+                    .or_default()
+                    .push(take(&mut bonus_deck, card));
+            }
+        }
+        stack(&mut event_deck, &scenario.event_deck_top);
+        stack(&mut bonus_deck, &scenario.bonus_deck_top);
+        stack(&mut damage_deck, &scenario.damage_deck_top);
+
+        let mut roles = BTreeMap::new();
+        for role_def in &config.roles {
+            let facilities = scenario.facilities.get(&role_def.name);
+            roles.insert(
+                role_def.name.clone(),
+                Role {
+                    name: role_def.name.clone(),
+                    facilities: *facilities.unwrap_or(&role_def.starting_facilities),
+                    facilites_damaged: 0,
+                    facility_cap: role_def.facility_cap,
+                    resource: role_def.resource.clone(),
+                    acted: false,
+                    resources: 0,
+                    abilities: role_def.abilities.clone(),
+                    ability_used: false,
+                    hand: hands.remove(&role_def.name).unwrap_or_default(),
+                    production: role_def.production_table(),
+                    build_cost: role_def.build_cost_table(),
+                },
+            );
+        }
+
+        GameState {
+            event_deck,
+            event_cards_in_play: Vec::new(),
+            persistent_events: Vec::new(),
+            bonus_deck,
+            bonus_discards: Vec::new(),
+            damage_deck,
+            event_discards: Vec::new(),
+            damage_discards: Vec::new(),
+            signals: Vec::new(),
+            year_number: scenario.year_number,
+            roles,
+            infra_damage: scenario.infra_damage,
+            damage_cards: config.damage_deck.clone(),
+            rules: config.rules.clone(),
+            event_log: Vec::new(),
+            history: Vec::new(),
+            rng,
+            verbose: true,
+        }
+    }
+}
+
+/// Removes one copy of `card` from `deck`. The config has checked it is there.
+fn take<T: PartialEq + Clone>(deck: &mut Vec<T>, card: &T) -> T {
+    let pos = deck.iter().position(|c| c == card).unwrap();
+    deck.remove(pos)
+}
+
+/// Moves `top` to the top of `deck` so that its first card is drawn first.
+fn stack<T: PartialEq + Clone>(deck: &mut Vec<T>, top: &[T]) {
+    for card in top.iter().rev() {
+        let card = take(deck, card);
+        deck.push(card);
+    }
+}
+
+/// Command line: a command word followed by `--flag value` pairs.
+struct Args {
+    command: String,
+    flags: HashMap<String, String>,
+}
+
+impl Args {
+    fn parse(args: &[String]) -> Result<Args, String> {
+        let mut rest = args.iter().skip(1).peekable();
+        let command = match rest.peek() {
+            Some(arg) if !arg.starts_with("--") => rest.next().unwrap().clone(),
+            _ => "play".to_string(),
+        };
+        let mut flags = HashMap::new();
+        while let Some(flag) = rest.next() {
+            if !flag.starts_with("--") {
+                return Err(format!("unexpected argument {}", flag));
+            }
+            let value = rest
+                .next()
+                .ok_or_else(|| format!("{} needs a value", flag))?;
+            flags.insert(flag[2..].to_string(), value.clone());
+        }
+        Ok(Args { command, flags })
+    }
+
+    fn get(&self, flag: &str) -> Option<&str> {
+        self.flags.get(flag).map(|value| value.as_str())
+    }
+
+    fn get_usize(&self, flag: &str, default: usize) -> Result<usize, String> {
+        match self.get(flag) {
+            Some(value) => value
+                .parse()
+                .map_err(|_| format!("--{} expects a number, got {}", flag, value)),
+            None => Ok(default),
+        }
+    }
+
+    /// The `--seed` given, or a random one to print so the game can be
+    /// played again.
+    fn seed(&self) -> Result<u64, String> {
+        match self.get("seed") {
+            Some(value) => value
+                .parse()
+                .map_err(|_| format!("--seed expects a number, got {}", value)),
+            None => Ok(thread_rng().gen()),
+        }
+    }
+
+    fn config(&self) -> Result<GameConfig, String> {
+        self.config_at(self.get("config"))
+    }
+
+    /// The config at `path`, or the standard game, set up from `--scenario`
+    /// if one is given.
+    fn config_at(&self, path: Option<&str>) -> Result<GameConfig, String> {
+        let config = match path {
+            Some(path) => GameConfig::load(path)?,
+            None => GameConfig::standard(),
+        };
+        match self.get("scenario") {
+            Some(path) => config.with_scenario(path),
+            None => Ok(config),
+        }
+    }
+
+    fn strategy(&self, default: &str) -> Result<Box<dyn Strategy>, String> {
+        self.strategy_named(self.get("strategy").unwrap_or(default))
+    }
+
+    fn strategy_named(&self, name: &str) -> Result<Box<dyn Strategy>, String> {
+        strategy::by_name(name).ok_or_else(|| {
+            format!(
+                "unknown strategy {}, expected one of {}",
+                name,
+                strategy::NAMES.join(", ")
+            )
+        })
+    }
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    match args.command.as_str() {
+        "play" => {
+            let seed = args.seed()?;
+            let mut gs = GameState::seeded(&args.config()?, seed);
+            let mut strategy = args.strategy("build")?;
+            strategy.reseed(seed);
+            println!("Seed {}", seed);
+            println!("Event deck length {}", gs.event_deck.len());
+            gs.play_game(strategy.as_mut());
+            println!("Game state at end {:?}", gs);
+        }
+        "batch" => {
+            let config = args.config()?;
+            let games = args.get_usize("games", 1000)?;
+            let report = batch::run_batch(&config, args.strategy("build")?.as_mut(), games);
+            print!("{}", report);
+            if let Some(path) = args.get("csv") {
+                series::save(path, &series::to_csv(&report.results))?;
+            }
+            if let Some(path) = args.get("json") {
+                series::save(path, &series::to_json(&report.results))?;
+            }
+        }
+        "report" => {
+            let config = args.config()?;
+            let games = args.get_usize("games", 1000)?;
+            let out = args.get("out").unwrap_or("report.html");
+            let report = batch::run_batch(&config, args.strategy("build")?.as_mut(), games);
+            let html = report::to_html(&report, args.get("config").unwrap_or("standard game"));
+            series::save(out, &html)?;
+            println!("Wrote {}", out);
+        }
+        "compare" => {
+            let a = args.config()?;
+            let b = match args.get("against") {
+                Some(path) => args.config_at(Some(path))?,
+                None => return Err("compare needs --against <config>".to_string()),
+            };
+            let games = args.get_usize("games", 1000)?;
+            let seed = args.seed()?;
+            let mut strategy = args.strategy("build")?;
+            print!(
+                "{}",
+                compare::compare(&a, &b, strategy.as_mut(), games, seed)
+            );
+        }
+        "replay" => {
+            let config = args.config()?;
+            let seed = args.seed()?;
+            let mut played = args.strategy("build")?;
+            let mut other = match args.get("with") {
+                Some(name) => args.strategy_named(name)?,
+                None => return Err("replay needs --with <strategy>".to_string()),
+            };
+            let replay = replay::replay(&config, seed, [played.as_mut(), other.as_mut()]);
+            print!("{}", replay);
+        }
+        "campaign" => {
+            let path = args
+                .get("campaign")
+                .ok_or_else(|| "campaign needs --campaign <file>".to_string())?;
+            let campaign = campaign::Campaign::load(path)?;
+            let campaigns = args.get_usize("games", 1000)?;
+            let seed = args.seed()?;
+            let mut strategy = args.strategy("build")?;
+            print!(
+                "{}",
+                campaign::run_campaigns(&campaign, strategy.as_mut(), campaigns, seed)
+            );
+        }
+        "golden" => {
+            if let Some(path) = args.get("record") {
+                let seed = args.seed()?;
+                let strategy = args.get("strategy").unwrap_or("steady");
+                golden::Golden::record(args.get("config"), seed, strategy)?.save(path)?;
+                println!("Recorded {}", path);
+            } else if let Some(path) = args.get("update") {
+                let old = golden::Golden::load(path)?;
+                golden::Golden::record(old.config.as_deref(), old.seed, &old.strategy)?
+                    .save(path)?;
+                println!("Updated {}", path);
+            } else {
+                let dir = args.get("dir").unwrap_or(golden::DIR);
+                let changed = golden::check(dir)?;
+                if !changed.is_empty() {
+                    return Err(changed.join("\n"));
+                }
+                println!("{} recorded games play out as recorded", golden::paths(dir)?.len());
+            }
+        }
+        "cards" => {
+            let out = args.get("out").unwrap_or("cards");
+            let sheets = cards::write_sheets(&args.config()?, out)?;
+            println!("Wrote {} sheets to {}", sheets, out);
+        }
+        "solve" => {
+            let path = args
+                .get("puzzle")
+                .ok_or_else(|| "solve needs --puzzle <file>".to_string())?;
+            let gs = puzzle::load(path, &args.config()?)?;
+            print!("{}", puzzle::solve(&gs));
+        }
+        "analyse" => {
+            let config = args.config()?;
+            let years = args.get_usize("years", 1)?;
+            let games = args.get_usize("games", 20000)?;
+            let mut strategy = args.strategy("steady")?;
+            let max_positions = args.get_usize("max-positions", 2_000_000)?;
+            let exact = analysis::analyse(&config, strategy.as_mut(), years, max_positions)?;
+            let sampled = analysis::monte_carlo(&config, strategy.as_mut(), years, games);
+            print!("{}", analysis::Comparison(&exact, &sampled));
+        }
+        other => {
+            return Err(format!(
+            "unknown command {}, expected play, batch, report, compare, replay, campaign, golden, cards, solve or analyse",
+            other
+        ))
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if let Err(e) = Args::parse(&args).and_then(|args| run(&args)) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Takes no trades, builds or repairs, so only the events change things.
+    struct PassAll;
+
+    impl Strategy for PassAll {
+        fn name(&self) -> &str {
+            "pass"
+        }
+
+        fn choose_action(&mut self, _obs: &Observation, _options: &[Action]) -> Action {
+            Action::Pass
+        }
+
+        fn fresh(&mut self) -> Box<dyn Strategy> {
+            Box::new(PassAll)
+        }
+    }
+
+    fn game() -> GameState {
+        let mut gs = GameState::seeded(&GameConfig::standard(), 1);
+        gs.verbose = false;
+        gs
+    }
+
+    fn dust_storm(damage: Option<DamageCard>, years: usize) -> EventCard {
+        EventCard::Persistent(
+            "Dust storm".to_string(),
+            vec![(RoleName::from("Joul"), RoleName::from("Joul"))],
             None,
-        ));
-        //Joul/Man/Hab issue, Joul/man/hab cost - system
-        event_deck.push(EventCard::Problem(
-            "Systemic".to_string(),
+            damage,
+            years,
+        )
+    }
+
+    #[test]
+    fn event_charging_a_role_twice_needs_a_resource_for_each_charge() {
+        let mut gs = game();
+        let hab = RoleName::from("Hab");
+        let twin = EventCard::Problem(
+            "Twin".to_string(),
             vec![
-                (RoleName::Joul, RoleName::Joul),
-                (RoleName::Man, RoleName::Man),
-                (RoleName::Hab, RoleName::Hab),
+                (hab.clone(), hab.clone()),
+                (RoleName::from("Joul"), hab.clone()),
             ],
             None,
-            None,
-        ));
-        //Joul/Man/Hab issue, man/hab/joul cost - system
-        event_deck.push(EventCard::Problem(
-            "Systemic".to_string(),
+            Some(DamageCard::InfrastructureDamage),
+        );
+        gs.roles.get_mut(&hab).unwrap().resources = 1;
+        assert!(!gs.can_deal_with_event(&twin));
+        gs.resolve_event(twin.clone(), &mut PassAll);
+        assert!(matches!(gs.event_log.last(), Some(LogEntry::Failed(..))));
+        assert_eq!(gs.roles[&hab].resources, 1);
+
+        gs.roles.get_mut(&hab).unwrap().resources = 2;
+        assert!(gs.can_deal_with_event(&twin));
+        gs.resolve_event(twin, &mut PassAll);
+        assert!(matches!(gs.event_log.last(), Some(LogEntry::Resolved(..))));
+        assert_eq!(gs.roles[&hab].resources, 0);
+    }
+
+    #[test]
+    fn resource_opportunity_adds_resources() {
+        let mut gs = game();
+        gs.event_cards_in_play = vec![EventCard::Opportunity(
+            "Supply drop".to_string(),
+            Opportunity::Resources(RoleName::from("Man"), 2),
+        )];
+        gs.resolve_year(&mut PassAll);
+        assert_eq!(gs.roles[&RoleName::from("Man")].resources, 2);
+        assert_eq!(
+            gs.event_log,
+            vec![LogEntry::Opportunity(0, "Supply drop".to_string())]
+        );
+    }
+
+    #[test]
+    fn resources_from_an_opportunity_pay_for_problems() {
+        let mut gs = game();
+        let storm = dust_storm(Some(DamageCard::InfrastructureDamage), 1);
+        gs.event_cards_in_play = vec![
+            storm,
+            EventCard::Opportunity(
+                "Solar flare".to_string(),
+                Opportunity::Resources(RoleName::from("Joul"), 1),
+            ),
+        ];
+        gs.resolve_year(&mut PassAll);
+        assert_eq!(gs.infra_damage, 0);
+        assert!(gs
+            .event_log
+            .contains(&LogEntry::Resolved(0, "Dust storm".to_string())));
+    }
+
+    #[test]
+    fn build_opportunity_is_free_and_leaves_the_role_free_to_act() {
+        let mut gs = game();
+        gs.roles.get_mut(&RoleName::from("Hab")).unwrap().resources = 1;
+        gs.resolve_event(
+            EventCard::Opportunity(
+                "Prefab module".to_string(),
+                Opportunity::Build(RoleName::from("Hab")),
+            ),
+            &mut PassAll,
+        );
+        let hab = &gs.roles[&RoleName::from("Hab")];
+        assert_eq!(hab.facilities, 2);
+        assert_eq!(hab.resources, 1);
+        assert!(!hab.acted);
+    }
+
+    #[test]
+    fn build_opportunity_respects_the_facility_cap() {
+        let mut gs = game();
+        gs.roles.get_mut(&RoleName::from("Hab")).unwrap().facilities = 4;
+        gs.resolve_event(
+            EventCard::Opportunity(
+                "Prefab module".to_string(),
+                Opportunity::Build(RoleName::from("Hab")),
+            ),
+            &mut PassAll,
+        );
+        assert_eq!(gs.roles[&RoleName::from("Hab")].facilities, 4);
+    }
+
+    #[test]
+    fn failed_persistent_event_comes_back_with_new_damage() {
+        let mut gs = game();
+        gs.event_cards_in_play = vec![dust_storm(Some(DamageCard::InfrastructureDamage), 2)];
+        gs.resolve_year(&mut PassAll);
+        assert_eq!(gs.infra_damage, 1);
+        assert_eq!(gs.persistent_events, vec![dust_storm(None, 1)]);
+
+        gs.return_persistent_events();
+        assert!(gs.persistent_events.is_empty());
+        assert_eq!(gs.event_cards_in_play.len(), 1);
+        assert!(gs.event_cards_in_play[0].damage().is_some());
+        assert_eq!(gs.events_dealt(), 1);
+    }
+
+    #[test]
+    fn quiet_draws_are_logged_and_can_be_left_out_of_the_count() {
+        let quiet_last = || {
             vec![
-                (RoleName::Joul, RoleName::Man),
-                (RoleName::Man, RoleName::Hab),
-                (RoleName::Hab, RoleName::Joul),
-            ],
-            None,
-            None,
-        ));
-        //Joul/Man/Hab issue, Joul/man/hab cost - system
-        event_deck.push(EventCard::Problem(
-            "Systemic".to_string(),
+                dust_storm(None, 1),
+                dust_storm(None, 1),
+                EventCard::NoProblem,
+            ]
+        };
+        let mut gs = game();
+        gs.event_deck = quiet_last();
+        gs.deal_event_and_damage_cards(2);
+        assert_eq!(gs.event_cards_in_play.len(), 2);
+
+        let mut gs = game();
+        gs.rules.quiet_events_count_as_draws = false;
+        gs.event_deck = quiet_last();
+        gs.deal_event_and_damage_cards(2);
+        assert_eq!(gs.event_cards_in_play.len(), 3);
+        assert_eq!(gs.event_cards_in_play[0], EventCard::NoProblem);
+        assert_eq!(gs.event_log[0], LogEntry::Drawn(0, EventCard::NoProblem));
+        assert_eq!(gs.events_dealt(), 2);
+    }
+
+    #[test]
+    fn returning_persistent_events_do_not_use_up_draws() {
+        let mut gs = game();
+        gs.persistent_events = vec![dust_storm(None, 1)];
+        let num = gs.get_num_event_cards();
+        gs.deal_event_and_damage_cards(num);
+        let drawn = gs.event_cards_in_play.len();
+        gs.return_persistent_events();
+        assert_eq!(gs.event_cards_in_play.len(), drawn + 1);
+    }
+
+    #[test]
+    fn persistent_event_leaves_play_when_its_years_run_out() {
+        let mut gs = game();
+        gs.event_cards_in_play = vec![dust_storm(Some(DamageCard::InfrastructureDamage), 1)];
+        gs.resolve_year(&mut PassAll);
+        assert_eq!(gs.infra_damage, 1);
+        assert!(gs.persistent_events.is_empty());
+    }
+
+    #[test]
+    fn paid_persistent_event_leaves_play() {
+        let mut gs = game();
+        gs.roles.get_mut(&RoleName::from("Joul")).unwrap().resources = 1;
+        gs.event_cards_in_play = vec![dust_storm(Some(DamageCard::InfrastructureDamage), 3)];
+        gs.resolve_year(&mut PassAll);
+        assert_eq!(gs.infra_damage, 0);
+        assert!(gs.persistent_events.is_empty());
+    }
+
+    #[test]
+    fn new_event_types_load_from_config_files() {
+        let config = GameConfig::from_json(
+            r#"{"event_deck": [
+                {"Opportunity": ["Supply drop", {"Resources": ["Man", 2]}]},
+                {"Opportunity": ["Prefab module", {"Build": "Hab"}]},
+                {"Persistent": ["Dust storm", [["Joul", "Joul"]], null, null, 2]}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(config.event_deck[2], dust_storm(None, 2));
+        assert!(GameConfig::load("configs/opportunities.json").is_ok());
+    }
+
+    #[test]
+    fn config_rejects_bad_new_event_types() {
+        let never = r#"{"event_deck": [
+            {"Persistent": ["Dust storm", [["Joul", "Joul"]], null, null, 0]}
+        ]}"#;
+        assert!(GameConfig::from_json(never).is_err());
+        let stranger = r#"{"event_deck": [
+            {"Opportunity": ["Supply drop", {"Resources": ["Bio", 2]}]}
+        ]}"#;
+        assert!(GameConfig::from_json(stranger).is_err());
+    }
+
+    #[test]
+    fn infrastructure_repair_charges_each_payer_and_removes_one_damage() {
+        let config =
+            GameConfig::from_json(r#"{"rules": {"repair_cost": ["Hab", "Joul", "Joul"]}}"#)
+                .unwrap();
+        let mut gs = GameState::seeded(&config, 1);
+        gs.verbose = false;
+        let joul = RoleName::from("Joul");
+        gs.infra_damage = 2;
+        for role in gs.roles.values_mut() {
+            role.resources = 1;
+        }
+        assert!(!gs.build_options().contains(&Action::Repair));
+
+        gs.roles.get_mut(&joul).unwrap().resources = 2;
+        assert!(gs.build_options().contains(&Action::Repair));
+        gs.take_build_action(Action::Repair);
+        assert_eq!(gs.infra_damage, 1);
+        let left: Vec<usize> = gs.roles.values().map(|role| role.resources).collect();
+        assert_eq!(left, vec![0, 0, 1]);
+        assert!(matches!(gs.event_log.last(), Some(LogEntry::Repaired(0))));
+
+        gs.infra_damage = 0;
+        for role in gs.roles.values_mut() {
+            role.resources = 5;
+        }
+        assert!(!gs.build_options().contains(&Action::Repair));
+
+        let mut standard = game();
+        standard.infra_damage = 2;
+        for role in standard.roles.values_mut() {
+            role.resources = 5;
+        }
+        assert!(!standard.build_options().contains(&Action::Repair));
+
+        assert!(GameConfig::from_json(r#"{"rules": {"repair_cost": ["Bio"]}}"#).is_err());
+    }
+
+    #[test]
+    fn persistent_facility_damage_idles_facilities_until_repaired() {
+        let man = RoleName::from("Man");
+        let mut gs = game();
+        gs.roles.get_mut(&man).unwrap().facilites_damaged = 1;
+        gs.produce();
+        assert_eq!(gs.roles[&man].facilites_damaged, 0);
+        for role in gs.roles.values_mut() {
+            role.resources = 5;
+        }
+        assert!(!gs
+            .build_options()
+            .contains(&Action::RepairFacility(man.clone())));
+
+        let config = GameConfig::load("configs/persistent_damage.json").unwrap();
+        let mut gs = GameState::seeded(&config, 1);
+        gs.verbose = false;
+        gs.roles.get_mut(&man).unwrap().facilities = 2;
+        gs.roles.get_mut(&man).unwrap().facilites_damaged = 1;
+        gs.produce();
+        gs.produce();
+        assert_eq!(gs.roles[&man].facilites_damaged, 1);
+        assert_eq!(batch::GameResult::from_game(&gs).facility_years_lost, 2);
+
+        let repair = Action::RepairFacility(man.clone());
+        let cost = gs.roles[&man].repair_cost();
+        for role in gs.roles.values_mut() {
+            role.resources = cost - 1;
+        }
+        assert!(!gs.build_options().contains(&repair));
+        for role in gs.roles.values_mut() {
+            role.resources = cost;
+        }
+        assert!(gs.build_options().contains(&repair));
+        gs.take_build_action(repair.clone());
+        assert_eq!(gs.roles[&man].facilites_damaged, 0);
+        assert!(gs.roles[&man].acted);
+        assert!(gs.roles.values().all(|role| role.resources == 0));
+        assert!(
+            matches!(gs.event_log.last(), Some(LogEntry::FacilityRepaired(0, role)) if role == &man)
+        );
+        assert_eq!(batch::GameResult::from_game(&gs).facility_repairs, 1);
+
+        gs.roles.get_mut(&man).unwrap().facilites_damaged = 1;
+        for role in gs.roles.values_mut() {
+            role.resources = cost;
+        }
+        assert!(!gs.build_options().contains(&repair));
+    }
+
+    #[test]
+    fn trades_are_offered_and_paid_at_the_trade_rate() {
+        let hab = RoleName::from("Hab");
+        let joul = RoleName::from("Joul");
+        let man = RoleName::from("Man");
+        let mut gs = game();
+        gs.roles.get_mut(&hab).unwrap().resources = 5;
+        assert!(gs.trade_options().is_empty());
+
+        let config = GameConfig::load("configs/trading.json").unwrap();
+        let mut gs = GameState::seeded(&config, 1);
+        gs.verbose = false;
+        gs.roles.get_mut(&hab).unwrap().resources = 2;
+        gs.roles.get_mut(&joul).unwrap().resources = 1;
+        gs.roles.get_mut(&man).unwrap().resources = 0;
+        assert_eq!(
+            gs.trade_options(),
             vec![
-                (RoleName::Joul, RoleName::Hab),
-                (RoleName::Man, RoleName::Joul),
-                (RoleName::Hab, RoleName::Man),
-            ],
-            None,
-            None,
-        ));
-        event_deck.push(EventCard::NoProblem);
-        event_deck.push(EventCard::NoProblem);
-        event_deck.push(EventCard::NoProblem);
-        event_deck.shuffle(&mut thread_rng());
-
-        let mut bonus_deck = Vec::new();
-        bonus_deck.push(BonusCard::Build(RoleName::Man));
-        bonus_deck.push(BonusCard::Build(RoleName::Man));
-        bonus_deck.push(BonusCard::Build(RoleName::Hab));
-        bonus_deck.push(BonusCard::Build(RoleName::Hab));
-        bonus_deck.push(BonusCard::Build(RoleName::Joul));
-        bonus_deck.push(BonusCard::Build(RoleName::Joul));
-        bonus_deck.push(BonusCard::Cancel(
-            "Systemic".to_string(),
-            vec![RoleName::Hab, RoleName::Joul, RoleName::Man],
-        ));
-        bonus_deck.push(BonusCard::Cancel(
-            "Systemic".to_string(),
-            vec![RoleName::Hab, RoleName::Joul, RoleName::Man],
-        ));
-        bonus_deck.push(BonusCard::Cancel(
-            "Quake".to_string(),
-            vec![RoleName::Joul, RoleName::Man],
-        ));
-        bonus_deck.push(BonusCard::Cancel(
-            "Quake".to_string(),
-            vec![RoleName::Joul, RoleName::Man],
-        ));
-        bonus_deck.push(BonusCard::Cancel(
-            "Meteor".to_string(),
-            vec![RoleName::Joul, RoleName::Man],
-        ));
-        bonus_deck.push(BonusCard::Cancel(
-            "Meteor".to_string(),
-            vec![RoleName::Joul, RoleName::Man],
-        ));
-        bonus_deck.push(BonusCard::Cancel("Mutiny".to_string(), vec![RoleName::Hab]));
-        bonus_deck.push(BonusCard::Cancel("Mutiny".to_string(), vec![RoleName::Hab]));
-        bonus_deck.push(BonusCard::Cancel(
-            "Raiding".to_string(),
-            vec![RoleName::Hab],
-        ));
-        bonus_deck.push(BonusCard::Cancel(
-            "Raiding".to_string(),
-            vec![RoleName::Hab],
-        ));
-        bonus_deck.push(BonusCard::Cancel(
-            "Spacecold".to_string(),
-            vec![RoleName::Hab],
-        ));
-        bonus_deck.push(BonusCard::Cancel(
-            "Spacecold".to_string(),
-            vec![RoleName::Hab],
-        ));
-        bonus_deck.shuffle(&mut thread_rng());
-
-        let mut damage_deck = Vec::new();
-        damage_deck.push(DamageCard::InfrastructureDamage);
-        damage_deck.push(DamageCard::InfrastructureDamage);
-        damage_deck.push(DamageCard::InfrastructureDamage);
-        damage_deck.push(DamageCard::FacilityDamage(RoleName::Hab));
-        damage_deck.push(DamageCard::FacilityDamage(RoleName::Man));
-        damage_deck.push(DamageCard::FacilityDamage(RoleName::Joul));
-        damage_deck.push(DamageCard::FacilityDestruction(RoleName::Hab));
-        damage_deck.push(DamageCard::FacilityDestruction(RoleName::Man));
-        damage_deck.push(DamageCard::FacilityDestruction(RoleName::Joul));
-        damage_deck.shuffle(&mut thread_rng());
-
-        let mut roles = HashMap::new();
-        roles.insert(
-            RoleName::Man,
-            Role {
-                name: RoleName::Man,
-                facilities: 1,
-                facilites_damaged: 0,
-                acted: false,
-                resources: 0,
-            },
+                Action::Trade(hab.clone(), joul.clone()),
+                Action::Trade(hab.clone(), man.clone())
+            ]
+        );
+        gs.trade(hab.clone(), joul.clone());
+        assert_eq!(gs.roles[&hab].resources, 0);
+        assert_eq!(gs.roles[&joul].resources, 2);
+        assert!(
+            matches!(gs.event_log.last(), Some(LogEntry::Traded(0, from, to)) if from == &hab && to == &joul)
+        );
+        assert_eq!(batch::GameResult::from_game(&gs).trades, 1);
+
+        let free = r#"{"rules": {"trade_rate": {"give": 0, "receive": 1}}}"#;
+        assert!(GameConfig::from_json(free).is_err());
+        let nothing = r#"{"rules": {"trade_rate": {"give": 1, "receive": 0}}}"#;
+        assert!(GameConfig::from_json(nothing).is_err());
+    }
+
+    fn with_ability(gs: &mut GameState, role: &str, ability: Ability) {
+        gs.roles
+            .get_mut(&RoleName::from(role))
+            .unwrap()
+            .abilities
+            .push(ability);
+    }
+
+    #[test]
+    fn extra_resource_needs_enough_working_facilities() {
+        let mut gs = game();
+        with_ability(&mut gs, "Joul", Ability::ExtraResource(3));
+        let joul = RoleName::from("Joul");
+        gs.roles.get_mut(&joul).unwrap().facilities = 2;
+        gs.produce();
+        assert_eq!(gs.roles[&joul].resources, 2);
+        gs.roles.get_mut(&joul).unwrap().facilities = 3;
+        gs.produce();
+        assert_eq!(gs.roles[&joul].resources, 4);
+    }
+
+    #[test]
+    fn ignored_damage_is_discarded() {
+        let mut gs = game();
+        with_ability(
+            &mut gs,
+            "Hab",
+            Ability::IgnoreDamage("Dust storm".to_string()),
         );
-        roles.insert(
-            RoleName::Joul,
-            Role {
-                name: RoleName::Joul,
-                facilities: 1,
-                facilites_damaged: 0,
-                acted: false,
-                resources: 0,
-            },
+        gs.event_cards_in_play = vec![dust_storm(Some(DamageCard::InfrastructureDamage), 1)];
+        gs.resolve_year(&mut PassAll);
+        assert_eq!(gs.infra_damage, 0);
+        assert_eq!(
+            gs.event_log,
+            vec![LogEntry::Shielded(
+                0,
+                "Dust storm".to_string(),
+                RoleName::from("Hab")
+            )]
         );
-        roles.insert(
-            RoleName::Hab,
-            Role {
-                name: RoleName::Hab,
-                facilities: 1,
-                facilites_damaged: 0,
-                acted: false,
-                resources: 0,
-            },
+    }
+
+    #[test]
+    fn infrastructure_damage_is_rerolled_once_a_year() {
+        let mut gs = game();
+        with_ability(&mut gs, "Man", Ability::RerollDamage);
+        let hab = RoleName::from("Hab");
+        gs.damage_deck = vec![
+            DamageCard::FacilityDamage(hab.clone()),
+            DamageCard::FacilityDamage(hab.clone()),
+        ];
+        let storm = dust_storm(Some(DamageCard::InfrastructureDamage), 1);
+        gs.event_cards_in_play = vec![storm.clone(), storm];
+        gs.resolve_year(&mut PassAll);
+        assert_eq!(gs.infra_damage, 1);
+        assert_eq!(gs.roles[&hab].facilites_damaged, 1);
+        assert_eq!(gs.damage_deck.len(), 1);
+
+        gs.produce();
+        assert!(!gs.roles[&RoleName::from("Man")].ability_used);
+    }
+
+    #[test]
+    fn bonus_cards_are_dealt_to_the_role_that_plays_them() {
+        let mut gs = game();
+        let joul = RoleName::from("Joul");
+        let man = RoleName::from("Man");
+        let cancel = BonusCard::Cancel("Meteor".to_string(), vec![joul.clone(), man.clone()]);
+        gs.bonus_deck = vec![cancel.clone(), BonusCard::Build(joul.clone())];
+        gs.deal_bonus_card(&mut PassAll);
+        gs.deal_bonus_card(&mut PassAll);
+        assert_eq!(gs.roles[&joul].hand, vec![BonusCard::Build(joul.clone())]);
+        assert_eq!(gs.roles[&man].hand, vec![cancel]);
+    }
+
+    #[test]
+    fn a_role_over_the_hand_limit_discards() {
+        let mut gs = game();
+        gs.rules.hand_limit = Some(1);
+        gs.rules.bonus_deal = BonusDeal::InTurn;
+        let hab = RoleName::from("Hab");
+        let build_joul = BonusCard::Build(RoleName::from("Joul"));
+        gs.roles.get_mut(&hab).unwrap().hand = vec![BonusCard::Build(hab.clone())];
+        gs.bonus_deck = vec![build_joul.clone()];
+        gs.deal_bonus_card(&mut PassAll);
+        assert_eq!(gs.roles[&hab].hand, vec![BonusCard::Build(hab.clone())]);
+        assert_eq!(gs.bonus_discards, vec![build_joul.clone()]);
+        assert_eq!(gs.event_log, vec![LogEntry::Discarded(0, hab, build_joul)]);
+    }
+
+    #[test]
+    fn cards_are_only_given_to_roles_that_can_play_them_and_have_room() {
+        let mut gs = game();
+        gs.rules.hand_limit = Some(1);
+        let hab = RoleName::from("Hab");
+        let joul = RoleName::from("Joul");
+        let man = RoleName::from("Man");
+        let cancel = BonusCard::Cancel("Meteor".to_string(), vec![joul.clone(), man.clone()]);
+        gs.roles.get_mut(&hab).unwrap().hand = vec![cancel.clone()];
+        gs.roles.get_mut(&joul).unwrap().hand = vec![BonusCard::Build(joul.clone())];
+        assert_eq!(
+            gs.give_options(),
+            vec![Action::Give(hab.clone(), man.clone(), cancel.clone())]
         );
 
-        println!("Event deck length {}", event_deck.len());
+        gs.give(hab.clone(), man.clone(), cancel.clone());
+        assert!(gs.roles[&hab].hand.is_empty());
+        assert_eq!(gs.roles[&man].hand, vec![cancel]);
+        assert!(gs.give_options().is_empty());
+    }
 
-        let gs = GameState {
-            bonus_cards_in_play: Vec::new(),
-            event_deck: event_deck,
-            event_cards_in_play: Vec::new(),
-            bonus_deck: bonus_deck,
-            damage_deck: damage_deck,
-            year_number: 0,
-            roles: roles,
-            infra_damage: 0,
+    #[test]
+    fn cancel_cards_are_played_only_by_roles_they_name() {
+        let mut gs = game();
+        let hab = RoleName::from("Hab");
+        let man = RoleName::from("Man");
+        let cancel = BonusCard::Cancel("Dust storm".to_string(), vec![man.clone()]);
+        gs.roles.get_mut(&hab).unwrap().hand = vec![cancel.clone()];
+        gs.resolve_event(
+            dust_storm(Some(DamageCard::InfrastructureDamage), 1),
+            &mut PassAll,
+        );
+        assert!(matches!(gs.event_log[0], LogEntry::Failed(..)));
+
+        let cancel = gs.roles.get_mut(&hab).unwrap().hand.remove(0);
+        gs.roles.get_mut(&man).unwrap().hand = vec![cancel.clone()];
+        gs.resolve_event(
+            dust_storm(Some(DamageCard::InfrastructureDamage), 1),
+            &mut PassAll,
+        );
+        assert_eq!(
+            gs.event_log[1],
+            LogEntry::Cancelled(0, "Dust storm".to_string(), man.clone())
+        );
+        assert!(gs.roles[&man].acted);
+        assert_eq!(gs.bonus_discards, vec![cancel]);
+    }
+
+    #[test]
+    fn thrifty_holds_a_cancel_card_while_the_roles_can_pay() {
+        let mut gs = game();
+        let joul = RoleName::from("Joul");
+        let cancel = BonusCard::Cancel("Dust storm".to_string(), vec![joul.clone()]);
+        gs.roles.get_mut(&joul).unwrap().hand = vec![cancel.clone()];
+        gs.roles.get_mut(&joul).unwrap().resources = 1;
+        let storm = dust_storm(Some(DamageCard::InfrastructureDamage), 1);
+        gs.resolve_event(storm.clone(), &mut strategy::Thrifty);
+        assert!(matches!(gs.event_log[0], LogEntry::Resolved(..)));
+        assert_eq!(gs.roles[&joul].hand, vec![cancel]);
+        assert_eq!(batch::GameResult::from_game(&gs).bonus_cards_expired, 1);
+
+        gs.resolve_event(storm, &mut strategy::Thrifty);
+        assert!(matches!(gs.event_log[1], LogEntry::Cancelled(..)));
+        let result = batch::GameResult::from_game(&gs);
+        assert_eq!(result.bonus_cards_played, 1);
+        assert_eq!(result.bonus_cards_expired, 0);
+    }
+
+    #[test]
+    fn thrifty_holds_a_build_card_at_the_facility_cap() {
+        let mut gs = game();
+        let hab = RoleName::from("Hab");
+        let role = gs.roles.get_mut(&hab).unwrap();
+        role.hand = vec![BonusCard::Build(hab.clone())];
+        role.facilities = role.facility_cap;
+        gs.build_with_bonus_cards(&mut strategy::Thrifty);
+        assert_eq!(gs.roles[&hab].hand.len(), 1);
+
+        gs.roles.get_mut(&hab).unwrap().facilities -= 1;
+        gs.build_with_bonus_cards(&mut strategy::Thrifty);
+        assert!(gs.roles[&hab].hand.is_empty());
+        assert_eq!(gs.event_log, vec![LogEntry::BonusBuilt(0, hab)]);
+    }
+
+    /// A problem with `rule` that fails and deals `damage`.
+    fn failing(rule: DamageRule, damage: DamageCard) -> EventCard {
+        EventCard::Problem(
+            "Surge".to_string(),
+            vec![(RoleName::from("Joul"), RoleName::from("Joul"))],
+            Some(rule),
+            Some(damage),
+        )
+    }
+
+    #[test]
+    fn redirect_moves_facility_damage_but_not_infrastructure_damage() {
+        let mut gs = game();
+        let hab = RoleName::from("Hab");
+        let man = RoleName::from("Man");
+        gs.roles.get_mut(&man).unwrap().facilities = 2;
+        let rule = DamageRule::RedirectTo(hab.clone());
+        gs.resolve_event(
+            failing(rule.clone(), DamageCard::FacilityDestruction(man.clone())),
+            &mut PassAll,
+        );
+        assert_eq!(gs.roles[&man].facilities, 2);
+        assert_eq!(gs.roles[&hab].facilites_damaged, 1);
+
+        gs.resolve_event(
+            failing(rule, DamageCard::InfrastructureDamage),
+            &mut PassAll,
+        );
+        assert_eq!(gs.infra_damage, 1);
+    }
+
+    #[test]
+    fn redirect_to_weakest_hits_the_role_with_fewest_facilities() {
+        let mut gs = game();
+        for (name, facilities) in [("Hab", 3), ("Joul", 2), ("Man", 2)] {
+            gs.roles.get_mut(&RoleName::from(name)).unwrap().facilities = facilities;
+        }
+        let hab = RoleName::from("Hab");
+        gs.resolve_event(
+            failing(
+                DamageRule::RedirectToWeakest,
+                DamageCard::FacilityDestruction(hab.clone()),
+            ),
+            &mut PassAll,
+        );
+        assert_eq!(gs.roles[&hab].facilities, 3);
+        assert_eq!(gs.roles[&RoleName::from("Joul")].facilities, 1);
+        assert_eq!(gs.roles[&RoleName::from("Man")].facilities, 2);
+    }
+
+    #[test]
+    fn infrastructure_rule_turns_facility_damage_into_infrastructure_damage() {
+        let mut gs = game();
+        let hab = RoleName::from("Hab");
+        gs.resolve_event(
+            failing(
+                DamageRule::Infrastructure,
+                DamageCard::FacilityDamage(hab.clone()),
+            ),
+            &mut PassAll,
+        );
+        assert_eq!(gs.infra_damage, 1);
+        assert_eq!(gs.roles[&hab].facilites_damaged, 0);
+    }
+
+    #[test]
+    fn double_rule_deals_the_damage_twice() {
+        let mut gs = game();
+        let hab = RoleName::from("Hab");
+        gs.roles.get_mut(&hab).unwrap().facilities = 3;
+        gs.resolve_event(
+            failing(
+                DamageRule::Double,
+                DamageCard::FacilityDestruction(hab.clone()),
+            ),
+            &mut PassAll,
+        );
+        assert_eq!(gs.roles[&hab].facilities, 1);
+
+        gs.resolve_event(
+            failing(DamageRule::Double, DamageCard::InfrastructureDamage),
+            &mut PassAll,
+        );
+        assert_eq!(gs.infra_damage, 2);
+    }
+
+    #[test]
+    fn escalation_draws_extra_damage_cards_from_its_year() {
+        let mut gs = game();
+        gs.rules.escalation = vec![Escalation {
+            from_year: 3,
+            events: vec!["Surge".to_string()],
+            extra_damage_cards: 1,
+            destroy: false,
+        }];
+        let hab = RoleName::from("Hab");
+        gs.damage_deck = vec![DamageCard::FacilityDamage(hab.clone())];
+        let surge = failing(
+            DamageRule::RedirectTo(hab.clone()),
+            DamageCard::InfrastructureDamage,
+        );
+        gs.year_number = 2;
+        gs.resolve_event(surge.clone(), &mut PassAll);
+        assert_eq!(gs.damage_deck.len(), 1);
+
+        gs.year_number = 3;
+        gs.resolve_event(surge, &mut PassAll);
+        assert_eq!(gs.infra_damage, 2);
+        assert_eq!(gs.roles[&hab].facilites_damaged, 1);
+        assert_eq!(
+            gs.event_log[2],
+            LogEntry::ExtraDamage(3, "Surge".to_string(), DamageCard::FacilityDamage(hab))
+        );
+        assert_eq!(batch::GameResult::from_game(&gs).infra_hits, 2);
+    }
+
+    #[test]
+    fn escalation_turns_facility_damage_into_destruction() {
+        let mut gs = game();
+        gs.rules.escalation = vec![Escalation {
+            from_year: 6,
+            events: Vec::new(),
+            extra_damage_cards: 0,
+            destroy: true,
+        }];
+        let hab = RoleName::from("Hab");
+        gs.roles.get_mut(&hab).unwrap().facilities = 3;
+        gs.year_number = 6;
+        gs.resolve_event(
+            failing(DamageRule::Double, DamageCard::FacilityDamage(hab.clone())),
+            &mut PassAll,
+        );
+        assert_eq!(gs.roles[&hab].facilities, 1);
+        assert_eq!(gs.roles[&hab].facilites_damaged, 0);
+
+        let unknown = r#"{"rules": {"escalation": [{"from_year": 1, "events": ["Flood"]}]}}"#;
+        assert!(GameConfig::from_json(unknown).is_err());
+    }
+
+    #[test]
+    fn level_tables_set_production_and_build_costs() {
+        let role = |name: &str| {
+            format!(
+                r#"{{"name": "{}", "resource": "r", "production": [2, 1, 1, 0], "build_cost": [1, 1, 2]}}"#,
+                name
+            )
         };
-        gs
+        let json = format!(
+            r#"{{"roles": [{}, {}, {}]}}"#,
+            role("Hab"),
+            role("Joul"),
+            role("Man")
+        );
+        let config = GameConfig::from_json(&json).unwrap();
+        assert!(GameConfig::load("configs/diminishing.json").is_ok());
+        let mut gs = GameState::seeded(&config, 1);
+        gs.verbose = false;
+        let hab = RoleName::from("Hab");
+        gs.roles.get_mut(&hab).unwrap().facilities = 4;
+        gs.roles.get_mut(&hab).unwrap().facilites_damaged = 1;
+        gs.produce();
+        assert_eq!(gs.roles[&hab].resources, 4);
+        assert_eq!(gs.roles[&RoleName::from("Joul")].resources, 2);
+
+        let man = RoleName::from("Man");
+        gs.roles.get_mut(&man).unwrap().facilities = 3;
+        for role in gs.roles.values_mut() {
+            role.resources = 1;
+        }
+        assert!(!gs.build_options().contains(&Action::Build(man.clone())));
+        for role in gs.roles.values_mut() {
+            role.resources = 2;
+        }
+        gs.take_build_action(Action::Build(man.clone()));
+        assert_eq!(gs.roles[&man].facilities, 4);
+        assert!(gs.roles.values().all(|role| role.resources == 0));
+
+        gs.rules.persistent_facility_damage = true;
+        let repair = Action::RepairFacility(man.clone());
+        for role in gs.roles.values_mut() {
+            role.resources = 1;
+            role.acted = false;
+        }
+        gs.roles.get_mut(&man).unwrap().facilites_damaged = 1;
+        assert!(!gs.build_options().contains(&repair));
+        for role in gs.roles.values_mut() {
+            role.resources = 2;
+        }
+        assert!(gs.build_options().contains(&repair));
+        gs.take_build_action(repair);
+        assert_eq!(gs.roles[&man].facilites_damaged, 0);
+        assert!(gs.roles.values().all(|role| role.resources == 0));
+
+        let short = r#"{"roles": [
+            {"name": "Hab", "resource": "habitat", "production": [1, 1]},
+            {"name": "Joul", "resource": "energy"}
+        ]}"#;
+        assert!(GameConfig::from_json(short).is_err());
+        let costly = r#"{"roles": [
+            {"name": "Hab", "resource": "habitat", "facility_cap": 2, "build_cost": [1, 2]},
+            {"name": "Joul", "resource": "energy"}
+        ]}"#;
+        assert!(GameConfig::from_json(costly).is_err());
     }
-}
 
-fn main() {
-    let mut gs = GameState::new();
-    for _n in 0..9 {
-        if gs.infra_damage > 2 {
-            println!("Kerblooey!");
-            break;
+    #[test]
+    fn observation_hides_damage_and_counts_seen_cards() {
+        let mut gs = game();
+        let hab = RoleName::from("Hab");
+        gs.event_deck = vec![
+            EventCard::NoProblem,
+            failing(DamageRule::Double, DamageCard::InfrastructureDamage),
+            dust_storm(None, 1),
+        ];
+        gs.damage_cards = vec![
+            DamageCard::InfrastructureDamage,
+            DamageCard::FacilityDamage(hab.clone()),
+        ];
+        gs.damage_deck = vec![DamageCard::FacilityDamage(hab.clone())];
+        gs.event_cards_in_play = vec![dust_storm(Some(DamageCard::InfrastructureDamage), 1)];
+
+        let obs = gs.observe();
+        assert_eq!(obs.events_in_play(), vec![dust_storm(None, 1)]);
+        assert_eq!(obs.event_deck().values().sum::<usize>(), 3);
+        assert!((obs.chance_next_event_needs(&RoleName::from("Joul")) - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(obs.chance_next_event_needs(&hab), 0.0);
+        assert_eq!(obs.chance_of_infra_damage(), 0.5);
+
+        gs.resolve_year(&mut PassAll);
+        let obs = gs.observe();
+        assert_eq!(obs.damage_discards(), &[DamageCard::InfrastructureDamage]);
+        assert_eq!(obs.event_discards(), &[dust_storm(None, 1)]);
+        assert_eq!(obs.chance_of_infra_damage(), 0.0);
+
+        // A card drawn before a shuffle and turned over after it leaves more
+        // turned over than was printed.
+        gs.damage_discards = vec![DamageCard::InfrastructureDamage; 2];
+        let unseen = gs.observe().damage_deck();
+        assert_eq!(unseen.get(&DamageCard::InfrastructureDamage), None);
+        assert_eq!(unseen.get(&DamageCard::FacilityDamage(hab)), Some(&1));
+    }
+
+    #[test]
+    fn limited_talk_hides_hands_and_sends_signals() {
+        let mut gs = game();
+        let hab = RoleName::from("Hab");
+        let joul = RoleName::from("Joul");
+        let cancel = BonusCard::Cancel("Dust storm".to_string(), vec![joul.clone()]);
+        gs.rules.table_talk = TableTalk::Signals;
+        gs.roles.get_mut(&joul).unwrap().hand = vec![cancel];
+        gs.roles.get_mut(&joul).unwrap().resources = 0;
+        gs.event_cards_in_play = vec![dust_storm(Some(DamageCard::InfrastructureDamage), 1)];
+
+        gs.send_signals();
+        assert_eq!(
+            gs.signals,
+            vec![
+                (joul.clone(), Signal::CanCancel("Dust storm".to_string())),
+                (joul.clone(), Signal::Short),
+            ]
+        );
+        let obs = gs.observe_as(&hab);
+        assert!(obs.role(&joul).hand.is_empty());
+        assert_eq!(obs.hand_size(&joul), 1);
+        assert_eq!(obs.bonus_deck_size(), gs.bonus_deck.len());
+        assert!(obs.will_be_cancelled(&"Dust storm".to_string()));
+
+        gs.rules.table_talk = TableTalk::Silent;
+        gs.send_signals();
+        assert!(gs.signals.is_empty());
+        assert!(!gs
+            .observe_as(&hab)
+            .will_be_cancelled(&"Dust storm".to_string()));
+        assert!(gs
+            .observe_as(&joul)
+            .will_be_cancelled(&"Dust storm".to_string()));
+    }
+
+    #[test]
+    fn viewer_counts_its_own_cancel_when_another_role_holds_one_too() {
+        let mut gs = game();
+        let hab = RoleName::from("Hab");
+        let joul = RoleName::from("Joul");
+        let storm = "Dust storm".to_string();
+        gs.rules.table_talk = TableTalk::Silent;
+        for role in &[&joul, &hab] {
+            gs.roles.get_mut(*role).unwrap().hand =
+                vec![BonusCard::Cancel(storm.clone(), vec![(*role).clone()])];
+        }
+        gs.event_cards_in_play = vec![dust_storm(Some(DamageCard::InfrastructureDamage), 1)];
+
+        gs.send_signals();
+        assert!(gs.observe_as(&joul).will_be_cancelled(&storm));
+        assert!(gs.observe_as(&hab).will_be_cancelled(&storm));
+        assert!(!gs
+            .observe_as(&RoleName::from("Man"))
+            .will_be_cancelled(&storm));
+    }
+
+    #[test]
+    fn only_an_open_table_knows_what_is_left_in_the_bonus_deck() {
+        let gs = game();
+        let deck = counts(gs.bonus_deck.iter().cloned());
+        assert_eq!(gs.observe().bonus_deck(), Some(deck));
+        assert_eq!(gs.observe().bonus_deck_size(), gs.bonus_deck.len());
+
+        let hab = Observation::for_role(&gs, &RoleName::from("Hab"));
+        assert_eq!(hab.bonus_deck(), None);
+        assert_eq!(hab.bonus_deck_size(), gs.bonus_deck.len());
+        assert!(!hab.to_string().contains(" build, "));
+        assert!(gs.observe().to_string().contains(" build, "));
+    }
+
+    /// Passes, noting who was asked and what they could do.
+    struct Asked(Vec<(Option<RoleName>, Vec<Action>)>);
+
+    impl Strategy for Asked {
+        fn name(&self) -> &str {
+            "asked"
+        }
+
+        fn choose_action(&mut self, obs: &Observation, options: &[Action]) -> Action {
+            self.0.push((obs.viewer().cloned(), options.to_vec()));
+            Action::Pass
+        }
+
+        fn fresh(&mut self) -> Box<dyn Strategy> {
+            Box::new(Asked(Vec::new()))
+        }
+    }
+
+    #[test]
+    fn limited_talk_asks_each_role_for_its_own_actions() {
+        let mut gs = game();
+        for role in gs.roles.values_mut() {
+            role.resources = 1;
+        }
+        let options = gs.build_options();
+        let mut asked = Asked(Vec::new());
+        assert_eq!(gs.decide(&mut asked, &options), Action::Pass);
+        assert_eq!(asked.0, vec![(None, options.clone())]);
+
+        gs.rules.table_talk = TableTalk::Silent;
+        let mut asked = Asked(Vec::new());
+        assert_eq!(gs.decide(&mut asked, &options), Action::Pass);
+        let names = ["Hab", "Joul", "Man"].iter().map(|&n| RoleName::from(n));
+        let expected: Vec<_> = names
+            .map(|name| (Some(name.clone()), vec![Action::Build(name)]))
+            .collect();
+        assert_eq!(asked.0, expected);
+    }
+
+    /// Passes, and panics if asked on behalf of more than one viewer.
+    struct OneSeat(Option<Option<RoleName>>);
+
+    impl OneSeat {
+        fn sit(&mut self, obs: &Observation) {
+            let viewer = obs.viewer().cloned();
+            let seat = self.0.get_or_insert_with(|| viewer.clone());
+            assert_eq!(seat, &viewer, "one player asked for two roles");
         }
-        gs.play_year();
     }
-    println!("Game state at end {:?}", gs);
+
+    impl Strategy for OneSeat {
+        fn name(&self) -> &str {
+            "one seat"
+        }
+
+        fn choose_action(&mut self, obs: &Observation, _options: &[Action]) -> Action {
+            self.sit(obs);
+            Action::Pass
+        }
+
+        fn choose_discard(
+            &mut self,
+            obs: &Observation,
+            _role: &RoleName,
+            _hand: &[BonusCard],
+        ) -> usize {
+            self.sit(obs);
+            0
+        }
+
+        fn fresh(&mut self) -> Box<dyn Strategy> {
+            Box::new(OneSeat(None))
+        }
+    }
+
+    #[test]
+    fn limited_talk_gives_each_role_its_own_player() {
+        for talk in &[TableTalk::Signals, TableTalk::Silent] {
+            let mut gs = game();
+            gs.rules.table_talk = *talk;
+            for role in gs.roles.values_mut() {
+                role.resources = 1;
+            }
+            let mut strategy = OneSeat(None);
+            gs.resolve_year(&mut gs.seats(&mut strategy));
+            assert_eq!(strategy.0, None);
+        }
+    }
+
+    #[test]
+    fn campaign_carries_the_colony_into_the_next_chapter() {
+        let campaign = campaign::Campaign::load("configs/campaigns/first_landing.json").unwrap();
+        let decks: Vec<usize> = campaign
+            .chapters
+            .iter()
+            .map(|(_, config)| config.event_deck.len())
+            .collect();
+        assert_eq!(decks, vec![27, 29, 30]);
+
+        let games = (0..20)
+            .map(|seed| campaign.play(&mut strategy::Steady, seed))
+            .find(|games| games.len() > 1)
+            .unwrap();
+        for pair in games.windows(2) {
+            let (last, next) = (&pair[0], &pair[1]);
+            assert!(!last.game.collapsed());
+            assert_eq!(next.start_infra_damage, last.game.infra_damage);
+            let working: usize = last
+                .game
+                .roles
+                .values()
+                .map(|role| (role.facilities - role.facilites_damaged).max(1))
+                .sum();
+            assert_eq!(next.start_facilities, working);
+        }
+    }
+
+    /// How many of each card in `cards`.
+    fn counts<T: Ord + Clone>(cards: impl IntoIterator<Item = T>) -> BTreeMap<T, usize> {
+        let mut counts = BTreeMap::new();
+        for card in cards {
+            *counts.entry(card).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// `card` as printed, without its damage card or the years it has left.
+    fn face(card: EventCard) -> EventCard {
+        match card.with_damage(None) {
+            EventCard::Persistent(id, costs, rule, damage, _) => {
+                EventCard::Persistent(id, costs, rule, damage, 0)
+            }
+            card => card,
+        }
+    }
+
+    /// Checks what must hold between any two phases of a game of `config`.
+    /// `before` is the state at the end of the last phase checked. Resources
+    /// cannot go negative: they are unsigned and tests panic on underflow.
+    fn check_invariants(config: &GameConfig, gs: &GameState, before: &GameState, phase: &str) {
+        let at = format!("year {} after the {}", gs.year_number, phase);
+        for role in gs.roles.values() {
+            assert!(
+                role.facilities >= 1 && role.facilities <= role.facility_cap,
+                "{}: {:?} has {} facilities",
+                at,
+                role.name,
+                role.facilities
+            );
+            assert!(role.facilites_damaged <= role.facilities, "{}", at);
+        }
+
+        let mut events: Vec<EventCard> = gs.event_deck.clone();
+        events.extend(gs.event_cards_in_play.iter().cloned());
+        events.extend(gs.persistent_events.iter().cloned());
+        events.extend(gs.event_discards.iter().cloned());
+        let events = events.into_iter().map(face);
+        let printed = config.event_deck.iter().cloned().map(face);
+        assert_eq!(counts(events), counts(printed), "{}: event cards", at);
+
+        let mut bonus: Vec<BonusCard> = gs.bonus_deck.clone();
+        bonus.extend(gs.roles.values().flat_map(|role| role.hand.iter().cloned()));
+        bonus.extend(gs.bonus_discards.iter().cloned());
+        assert_eq!(
+            counts(bonus),
+            counts(config.bonus_deck.clone()),
+            "{}: bonus cards",
+            at
+        );
+
+        // A shuffle brings back the whole damage deck, even the cards lying
+        // under events in play, so only the deck itself is bounded by the
+        // printed cards.
+        let printed = counts(gs.damage_cards.clone());
+        for (card, n) in counts(gs.damage_deck.clone()) {
+            assert!(
+                n <= printed.get(&card).copied().unwrap_or(0),
+                "{}: {:?}",
+                at,
+                card
+            );
+        }
+
+        let repairs = gs.event_log[before.event_log.len()..]
+            .iter()
+            .filter(|entry| matches!(entry, LogEntry::Repaired(_)))
+            .count();
+        assert!(
+            gs.infra_damage + repairs >= before.infra_damage,
+            "{}: infrastructure damage fell from {} to {} with {} repairs",
+            at,
+            before.infra_damage,
+            gs.infra_damage,
+            repairs
+        );
+    }
+
+    #[test]
+    fn random_games_keep_the_invariants() {
+        let mut configs = vec![GameConfig::standard()];
+        for name in &[
+            "abilities",
+            "diminishing",
+            "escalation",
+            "four_roles",
+            "hands",
+            "opportunities",
+            "persistent_damage",
+            "repair",
+            "small_deck",
+            "talk_signals",
+            "trading",
+        ] {
+            configs.push(GameConfig::load(&format!("configs/{}.json", name)).unwrap());
+        }
+        let mut rng = ChaCha20Rng::seed_from_u64(49);
+        for game in 0..300 {
+            let config = configs.choose(&mut rng).unwrap();
+            let name = strategy::NAMES.choose(&mut rng).unwrap();
+            let mut strategy = strategy::by_name(name).unwrap();
+            strategy.reseed(game);
+            let mut gs = GameState::seeded(config, game);
+            gs.verbose = false;
+            let mut before = gs.clone();
+            check_invariants(config, &gs, &before, "setup");
+            while gs.year_number < YEARS_PER_GAME && !gs.collapsed() {
+                let year = gs.year_number;
+                gs.good_phase(strategy.as_mut());
+                check_invariants(config, &gs, &before, "good phase");
+                before = gs.clone();
+                gs.event_phase();
+                check_invariants(config, &gs, &before, "event phase");
+                before = gs.clone();
+                gs.resolve_year(strategy.as_mut());
+                check_invariants(config, &gs, &before, "action phase");
+                before = gs.clone();
+                assert_eq!(gs.year_number, year + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn play_year_moves_on_one_year() {
+        let mut strategy = strategy::Steady;
+        for seed in 0..20 {
+            let mut gs = GameState::seeded(&GameConfig::standard(), seed);
+            gs.verbose = false;
+            gs.play_game(&mut strategy);
+            let years: Vec<usize> = gs.history.iter().map(|year| year.year_number).collect();
+            assert_eq!(years, (1..=gs.year_number).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn recorded_games_play_out_as_recorded() {
+        let changed = golden::check(golden::DIR).unwrap();
+        assert!(changed.is_empty(), "{}", changed.join("\n"));
+        assert!(!golden::paths(golden::DIR).unwrap().is_empty());
+    }
+
+    #[test]
+    fn recorded_games_show_the_colony_after_each_year() {
+        let mut golden = golden::Golden::record(None, 1, "steady").unwrap();
+        let years = golden
+            .log
+            .iter()
+            .filter(|line| line.starts_with("YearSnapshot"))
+            .count();
+        assert_eq!(years, YEARS_PER_GAME);
+        assert!(golden.log.last().unwrap().starts_with("YearSnapshot"));
+
+        // A rule change that only moved facilities would log the same events.
+        let last = golden.log.len() - 1;
+        golden.log[last] = golden.log[last].replacen("facilities: ", "facilities: 9", 1);
+        let (line, ..) = golden.first_difference().unwrap().unwrap();
+        assert_eq!(line, last + 1);
+    }
 }
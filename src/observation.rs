@@ -0,0 +1,282 @@
+use crate::{
+    BonusCard, DamageCard, EventCard, EventCardID, GameState, Role, RoleName, Rules, Signal,
+};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// What the players can see of a game: the colony, the hands, the faces of
+/// the events in play and the discard piles. Deck order and the damage
+/// cards lying face down under events are hidden, so a strategy that only
+/// looks through an `Observation` cannot peek. An observation made for
+/// one role also hides the other roles' hands.
+pub struct Observation<'a> {
+    gs: &'a GameState,
+    viewer: Option<RoleName>,
+}
+
+/// How many of each card there are.
+pub type Composition<T> = BTreeMap<T, usize>;
+
+fn composition<'a, T: Ord + Clone + 'a>(cards: impl IntoIterator<Item = &'a T>) -> Composition<T> {
+    let mut counts = Composition::new();
+    for card in cards {
+        *counts.entry(card.clone()).or_insert(0) += 1;
+    }
+    counts
+}
+
+impl<'a> Observation<'a> {
+    pub fn new(gs: &'a GameState) -> Observation<'a> {
+        Observation { gs, viewer: None }
+    }
+
+    /// The game as `role` sees it when it cannot see the other hands.
+    pub fn for_role(gs: &'a GameState, role: &RoleName) -> Observation<'a> {
+        Observation {
+            gs,
+            viewer: Some(role.clone()),
+        }
+    }
+
+    /// The role this is seen by, if only one.
+    pub fn viewer(&self) -> Option<&RoleName> {
+        self.viewer.as_ref()
+    }
+
+    pub fn year_number(&self) -> usize {
+        self.gs.year_number
+    }
+
+    pub fn infra_damage(&self) -> usize {
+        self.gs.infra_damage
+    }
+
+    pub fn rules(&self) -> &Rules {
+        &self.gs.rules
+    }
+
+    /// A role as the viewer sees it, with an empty hand if it is not the
+    /// viewer's own.
+    pub fn role(&self, name: &RoleName) -> Role {
+        let mut role = self.gs.roles[name].clone();
+        if self.viewer.as_ref().is_some_and(|viewer| viewer != name) {
+            role.hand.clear();
+        }
+        role
+    }
+
+    /// Every role, in order of name.
+    pub fn roles(&self) -> Vec<Role> {
+        self.gs.roles.keys().map(|name| self.role(name)).collect()
+    }
+
+    /// How many cards a role holds, which everyone can see.
+    pub fn hand_size(&self, name: &RoleName) -> usize {
+        self.gs.roles[name].hand.len()
+    }
+
+    /// What the roles told each other this year.
+    pub fn signals(&self) -> &[(RoleName, Signal)] {
+        &self.gs.signals
+    }
+
+    /// The events in play, with their damage cards still face down.
+    pub fn events_in_play(&self) -> Vec<EventCard> {
+        self.gs
+            .event_cards_in_play
+            .iter()
+            .map(|card| card.clone().with_damage(None))
+            .collect()
+    }
+
+    pub fn event_discards(&self) -> &[EventCard] {
+        &self.gs.event_discards
+    }
+
+    pub fn bonus_discards(&self) -> &[BonusCard] {
+        &self.gs.bonus_discards
+    }
+
+    /// Damage cards turned face up since the damage deck was last shuffled.
+    pub fn damage_discards(&self) -> &[DamageCard] {
+        &self.gs.damage_discards
+    }
+
+    /// What is left in the event deck. Every event drawn is seen, so this is
+    /// known exactly.
+    pub fn event_deck(&self) -> Composition<EventCard> {
+        composition(&self.gs.event_deck)
+    }
+
+    /// What is left in the bonus deck. Every bonus card dealt goes to a
+    /// hand, so this is known exactly when every hand is in view. A single
+    /// role cannot see the other hands and is not told.
+    pub fn bonus_deck(&self) -> Option<Composition<BonusCard>> {
+        match self.viewer {
+            None => Some(composition(&self.gs.bonus_deck)),
+            Some(_) => None,
+        }
+    }
+
+    /// How many cards are left in the bonus deck, which anyone can see.
+    pub fn bonus_deck_size(&self) -> usize {
+        self.gs.bonus_deck.len()
+    }
+
+    /// Damage cards not seen since the last shuffle. This is the damage deck
+    /// as far as the players can tell: it includes the cards under events in
+    /// play and those drawn for events that were dealt with, which nobody
+    /// turned over.
+    pub fn damage_deck(&self) -> Composition<DamageCard> {
+        let mut unseen = composition(&self.gs.damage_cards);
+        for card in &self.gs.damage_discards {
+            // A card drawn before the last shuffle can be turned over after
+            // it, so there may be more turned over than printed.
+            if let Some(n) = unseen.get_mut(card) {
+                *n = n.saturating_sub(1);
+            }
+        }
+        unseen.retain(|_, n| *n > 0);
+        unseen
+    }
+
+    /// Chance that the next event drawn asks `role` to pay resources.
+    pub fn chance_next_event_needs(&self, role: &RoleName) -> f64 {
+        let deck = &self.gs.event_deck;
+        if deck.is_empty() {
+            return 0.0;
+        }
+        let needing = deck
+            .iter()
+            .filter(|card| card.role_costs().iter().any(|(_, cost)| cost == role))
+            .count();
+        needing as f64 / deck.len() as f64
+    }
+
+    /// Chance that the next damage card turned over is infrastructure
+    /// damage, judged from the cards not yet seen. Once every card has been
+    /// seen the deck is shuffled afresh.
+    pub fn chance_of_infra_damage(&self) -> f64 {
+        let mut unseen = self.damage_deck();
+        if unseen.is_empty() {
+            unseen = composition(&self.gs.damage_cards);
+        }
+        let total: usize = unseen.values().sum();
+        let infra = unseen
+            .get(&DamageCard::InfrastructureDamage)
+            .copied()
+            .unwrap_or(0);
+        if total == 0 {
+            0.0
+        } else {
+            infra as f64 / total as f64
+        }
+    }
+
+    /// Whether a role is known to hold a card that cancels `id`: any role
+    /// when every hand is in view, otherwise the viewer or a role that
+    /// signalled it.
+    pub fn will_be_cancelled(&self, id: &EventCardID) -> bool {
+        match &self.viewer {
+            None => self.gs.find_cancel(id).is_some(),
+            Some(viewer) => self.gs.roles[viewer].can_cancel(id)
+                || self.gs.signals.iter().any(
+                    |(_, signal)| matches!(signal, Signal::CanCancel(cancelled) if cancelled == id),
+                ),
+        }
+    }
+
+    /// Whether the roles can pay for `event` with the resources they have.
+    pub fn can_pay(&self, event: &EventCard) -> bool {
+        self.gs.can_deal_with_event(event)
+    }
+}
+
+/// What the table shows, for following a game.
+impl<'a> fmt::Display for Observation<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Year {}, infrastructure damage {}",
+            self.year_number() + 1,
+            self.infra_damage()
+        )?;
+        if let Some(viewer) = self.viewer() {
+            writeln!(f, "  As seen by {:?}", viewer)?;
+        }
+        for role in self.roles() {
+            let hand = if role.hand.len() == self.hand_size(&role.name) {
+                format!("{:?}", role.hand)
+            } else {
+                format!("{} cards", self.hand_size(&role.name))
+            };
+            writeln!(
+                f,
+                "  {:?}: {} facilities ({} damaged), {} {}, hand {}",
+                role.name,
+                role.facilities,
+                role.facilites_damaged,
+                role.resources,
+                role.resource,
+                hand
+            )?;
+        }
+        for (role, signal) in self.signals() {
+            writeln!(f, "  {:?} signalled {:?}", role, signal)?;
+        }
+        let in_play: Vec<String> = self
+            .events_in_play()
+            .iter()
+            .map(|card| {
+                card.id()
+                    .cloned()
+                    .unwrap_or_else(|| "All quiet".to_string())
+            })
+            .collect();
+        writeln!(f, "  In play: {}", in_play.join(", "))?;
+        let bonus_kinds = match self.bonus_deck() {
+            Some(deck) => {
+                let builds: usize = deck
+                    .iter()
+                    .filter(|(card, _)| matches!(card, BonusCard::Build(_)))
+                    .map(|(_, n)| n)
+                    .sum();
+                format!(
+                    " ({} build, {} cancel)",
+                    builds,
+                    self.bonus_deck_size() - builds
+                )
+            }
+            None => String::new(),
+        };
+        writeln!(
+            f,
+            "  Event deck {} left, {} discarded; bonus deck {} left{}, {} discarded; \
+             {} damage cards unseen, {} turned over",
+            self.event_deck().values().sum::<usize>(),
+            self.event_discards().len(),
+            self.bonus_deck_size(),
+            bonus_kinds,
+            self.bonus_discards().len(),
+            self.damage_deck().values().sum::<usize>(),
+            self.damage_discards().len()
+        )?;
+        let needs: Vec<String> = self
+            .roles()
+            .iter()
+            .map(|role| {
+                format!(
+                    "{:?} {:.0}%",
+                    role.name,
+                    100.0 * self.chance_next_event_needs(&role.name)
+                )
+            })
+            .collect();
+        write!(
+            f,
+            "  Next event needs {}; next damage is infrastructure {:.0}%",
+            needs.join(", "),
+            100.0 * self.chance_of_infra_damage()
+        )
+    }
+}
@@ -0,0 +1,544 @@
+use crate::config::GameConfig;
+use crate::observation::Observation;
+use crate::series::RoleSnapshot;
+use crate::strategy::{Action, Steady, Strategy};
+use crate::{Ability, DamageCard, EventCard, EventCardID, GameState, LogEntry, RoleName};
+use serde::Deserialize;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+
+/// A year's action phase as it stands once the events are dealt and the
+/// planning phase is over: who has what, and what has to be faced.
+#[derive(Deserialize)]
+struct Puzzle {
+    #[serde(default)]
+    year_number: usize,
+    #[serde(default)]
+    infra_damage: usize,
+    roles: BTreeMap<RoleName, RoleSnapshot>,
+    /// Events in play, each problem with its damage card attached.
+    events: Vec<EventCard>,
+}
+
+/// Reads the puzzle at `path` and sets it up as a game of `config`.
+pub fn load(path: &str, config: &GameConfig) -> Result<GameState, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let puzzle: Puzzle = serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))?;
+    setup(&puzzle, config).map_err(|e| format!("{}: {}", path, e))
+}
+
+fn setup(puzzle: &Puzzle, config: &GameConfig) -> Result<GameState, String> {
+    if let Some(role) = config
+        .roles
+        .iter()
+        .find(|role| role.abilities.contains(&Ability::RerollDamage))
+    {
+        return Err(format!(
+            "role {:?} rerolls damage, which depends on cards the puzzle does not fix",
+            role.name
+        ));
+    }
+    if config
+        .rules
+        .escalation
+        .iter()
+        .any(|e| e.extra_damage_cards > 0)
+    {
+        return Err(
+            "escalation draws extra damage cards, which the puzzle does not fix".to_string(),
+        );
+    }
+    let mut gs = GameState::seeded(config, 0);
+    gs.verbose = false;
+    gs.year_number = puzzle.year_number;
+    gs.infra_damage = puzzle.infra_damage;
+    for def in &config.roles {
+        let given = puzzle
+            .roles
+            .get(&def.name)
+            .ok_or_else(|| format!("role {:?} is missing", def.name))?;
+        if given.facilities < 1 || given.facilities > def.facility_cap {
+            return Err(format!(
+                "role {:?} has {} facilities but its cap is {}",
+                def.name, given.facilities, def.facility_cap
+            ));
+        }
+        if given.damaged > given.facilities {
+            return Err(format!(
+                "role {:?} has more damaged facilities than facilities",
+                def.name
+            ));
+        }
+        let role = gs.roles.get_mut(&def.name).unwrap();
+        role.facilities = given.facilities;
+        role.facilites_damaged = given.damaged;
+        role.resources = given.resources;
+        role.hand = given.hand.clone();
+    }
+    if let Some(name) = puzzle
+        .roles
+        .keys()
+        .find(|name| !gs.roles.contains_key(name))
+    {
+        return Err(format!("role {:?} is not in the config", name));
+    }
+    for card in &puzzle.events {
+        if card.is_problem() && card.damage().is_none() {
+            return Err(format!("event {:?} has no damage card", card.id()));
+        }
+    }
+    gs.event_cards_in_play = puzzle.events.clone();
+    Ok(gs)
+}
+
+/// One way through the action and build phases.
+pub struct Play {
+    /// The order the events were resolved in.
+    pub order: Vec<EventCard>,
+    /// How each problem went, in that order.
+    pub log: Vec<LogEntry>,
+    /// Problems a bonus card could have cancelled but was held back from.
+    pub held: Vec<EventCardID>,
+    pub builds: Vec<Action>,
+    /// Resolution orders that lead to the same play.
+    pub orders: usize,
+    result: GameState,
+}
+
+impl Play {
+    /// Better plays score higher: survival first, then the least
+    /// infrastructure damage, the most working facilities, the most
+    /// facilities and the most bonus cards kept for later years.
+    fn score(&self) -> (bool, Reverse<usize>, usize, usize, usize) {
+        let gs = &self.result;
+        let facilities: usize = gs.roles.values().map(|r| r.facilities).sum();
+        let damaged: usize = gs.roles.values().map(|r| r.facilites_damaged).sum();
+        (
+            !gs.collapsed(),
+            Reverse(gs.infra_damage),
+            facilities - damaged,
+            facilities,
+            gs.bonus_cards_held(),
+        )
+    }
+
+    /// Damage cards of the events this play cancelled, paid for or was
+    /// shielded from. Each problem ends in exactly one of these entries or
+    /// `Failed`; what else its damage logs is skipped.
+    pub fn avoided(&self) -> Vec<&DamageCard> {
+        let problems = self.order.iter().filter_map(|card| card.damage());
+        let outcomes = self.log.iter().filter(|entry| {
+            matches!(
+                entry,
+                LogEntry::Cancelled(..)
+                    | LogEntry::Resolved(..)
+                    | LogEntry::Failed(..)
+                    | LogEntry::Shielded(..)
+            )
+        });
+        problems
+            .zip(outcomes)
+            .filter(|(_, entry)| !matches!(entry, LogEntry::Failed(..)))
+            .map(|(damage, _)| damage)
+            .collect()
+    }
+
+    fn describe(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let order: Vec<String> = self
+            .order
+            .iter()
+            .filter(|card| card.is_problem())
+            .filter_map(|card| card.id().cloned())
+            .collect();
+        let steps: Vec<String> = self
+            .log
+            .iter()
+            .map(|entry| match entry {
+                LogEntry::Cancelled(_, id, role) => format!("cancel {} ({:?} acts)", id, role),
+                LogEntry::Resolved(_, id) => format!("pay for {}", id),
+                LogEntry::Failed(_, id, damage) => format!("{} fails: {:?}", id, damage),
+                other => format!("{:?}", other),
+            })
+            .collect();
+        writeln!(
+            f,
+            "  Resolve {} ({} of the orders give this play)",
+            order.join(", "),
+            self.orders
+        )?;
+        writeln!(f, "    {}", steps.join("; "))?;
+        if !self.held.is_empty() {
+            writeln!(f, "    Holds the bonus cards for {}", self.held.join(", "))?;
+        }
+        if self.builds.is_empty() {
+            writeln!(f, "    No builds")?;
+        } else {
+            writeln!(f, "    Then {:?}", self.builds)?;
+        }
+        writeln!(f, "    Avoids {:?}", self.avoided())?;
+        let (_, Reverse(infra), working, facilities, _) = self.score();
+        writeln!(
+            f,
+            "    Ends with infrastructure damage {}, {} facilities ({} working)",
+            infra, facilities, working
+        )
+    }
+}
+
+/// Every distinct play of a puzzle, best first.
+pub struct Solution {
+    pub orders: usize,
+    pub plays: Vec<Play>,
+}
+
+impl Solution {
+    pub fn optimal(&self) -> &[Play] {
+        let best = match self.plays.first() {
+            Some(play) => play.score(),
+            None => return &[],
+        };
+        let count = self.plays.iter().take_while(|p| p.score() == best).count();
+        &self.plays[..count]
+    }
+}
+
+impl fmt::Display for Solution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} distinct plays from {} resolution orders",
+            self.plays.len(),
+            self.orders
+        )?;
+        let optimal = self.optimal();
+        writeln!(f, "{} optimal:", optimal.len())?;
+        for play in optimal {
+            play.describe(f)?;
+        }
+        if let Some(worst) = self.plays.last() {
+            if worst.score() != optimal[0].score() {
+                writeln!(f, "Worst play, for comparison:")?;
+                worst.describe(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Orders of `cards` that differ in at least one position.
+fn orders(cards: &[EventCard]) -> Vec<Vec<EventCard>> {
+    if cards.is_empty() {
+        return vec![Vec::new()];
+    }
+    let mut seen = HashSet::new();
+    let mut result = Vec::new();
+    for (i, card) in cards.iter().enumerate() {
+        if !seen.insert(card) {
+            continue;
+        }
+        let mut rest = cards.to_vec();
+        rest.remove(i);
+        for mut order in orders(&rest) {
+            order.insert(0, card.clone());
+            result.push(order);
+        }
+    }
+    result
+}
+
+/// Resolves like `Steady`, but plays a cancel card only if told to.
+struct Cancel(bool);
+
+impl Strategy for Cancel {
+    fn name(&self) -> &str {
+        "cancel"
+    }
+
+    fn choose_action(&mut self, obs: &Observation, options: &[Action]) -> Action {
+        Steady.choose_action(obs, options)
+    }
+
+    fn play_cancel(&mut self, _obs: &Observation, _role: &RoleName, _event: &EventCard) -> bool {
+        self.0
+    }
+
+    fn fresh(&mut self) -> Box<dyn Strategy> {
+        Box::new(Cancel(self.0))
+    }
+}
+
+/// Every way of resolving `cards` in order from `gs`: a problem a bonus
+/// card could cancel is cancelled in one line of play and paid for or
+/// failed in the other. Each ends with the problems the card was held from.
+fn resolutions(
+    gs: &GameState,
+    cards: &[EventCard],
+    held: &mut Vec<EventCardID>,
+    out: &mut Vec<(GameState, Vec<EventCardID>)>,
+) {
+    let (card, rest) = match cards.split_first() {
+        Some(split) => split,
+        None => {
+            out.push((gs.clone(), held.clone()));
+            return;
+        }
+    };
+    let mut played = gs.clone();
+    played.resolve_event(card.clone(), &mut Cancel(true));
+    resolutions(&played, rest, held, out);
+    let cancellable = card.is_problem() && card.id().is_some_and(|id| gs.find_cancel(id).is_some());
+    if cancellable {
+        let mut kept = gs.clone();
+        kept.resolve_event(card.clone(), &mut Cancel(false));
+        held.push(card.id().unwrap().clone());
+        resolutions(&kept, rest, held, out);
+        held.pop();
+    }
+}
+
+/// Every set of build phase actions from `gs`, each taken in sorted order
+/// since the actions' costs and effects do not depend on their order.
+fn builds(gs: &GameState, done: &mut Vec<Action>, out: &mut Vec<(Vec<Action>, GameState)>) {
+    let mut end = gs.clone();
+    end.build_with_bonus_cards(&mut Steady);
+    out.push((done.clone(), end));
+    for action in gs.build_options() {
+        if done.last().is_some_and(|last| &action < last) {
+            continue;
+        }
+        let mut next = gs.clone();
+        next.take_build_action(action.clone());
+        done.push(action);
+        builds(&next, done, out);
+        done.pop();
+    }
+}
+
+/// Tries every order of resolving the events in play, each followed by
+/// every choice of builds and repairs. Events are resolved as in a game:
+/// with a matching bonus card if there is one and it is played, with
+/// resources if the roles can pay, and otherwise they fail. Every cancel
+/// card is tried both played and held back; build cards are always played
+/// and opportunities always taken first. Plays that differ only in the
+/// order of events with the same results are counted once.
+pub fn solve(gs: &GameState) -> Solution {
+    let (opportunities, problems): (Vec<EventCard>, Vec<EventCard>) = gs
+        .event_cards_in_play
+        .iter()
+        .filter(|card| card != &&EventCard::NoProblem)
+        .cloned()
+        .partition(|card| matches!(card, EventCard::Opportunity(..)));
+    let orders = orders(&problems);
+    let mut plays: Vec<Play> = Vec::new();
+    let mut index: HashMap<(Vec<LogEntry>, Vec<Action>), usize> = HashMap::new();
+    for order in &orders {
+        let mut state = gs.clone();
+        for card in &opportunities {
+            state.resolve_event(card.clone(), &mut Steady);
+        }
+        let logged = state.event_log.len();
+        let mut resolved = Vec::new();
+        resolutions(&state, order, &mut Vec::new(), &mut resolved);
+        for (mut state, held) in resolved {
+            state.event_cards_in_play = Vec::new();
+            let log = state.event_log[logged..].to_vec();
+            let mut outcomes = Vec::new();
+            builds(&state, &mut Vec::new(), &mut outcomes);
+            for (actions, result) in outcomes {
+                let mut key_log = log.clone();
+                key_log.sort();
+                let key = (key_log, actions.clone());
+                match index.get(&key) {
+                    Some(&i) => plays[i].orders += 1,
+                    None => {
+                        index.insert(key, plays.len());
+                        plays.push(Play {
+                            order: order.clone(),
+                            log: log.clone(),
+                            held: held.clone(),
+                            builds: actions,
+                            orders: 1,
+                            result,
+                        });
+                    }
+                }
+            }
+        }
+    }
+    plays.sort_by_key(|play| Reverse(play.score()));
+    Solution {
+        orders: orders.len(),
+        plays,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BonusCard, DamageRule};
+    use serde_json::{json, Value};
+
+    const PUZZLE: &str = "configs/puzzles/short_of_energy.json";
+
+    fn game() -> GameState {
+        let mut gs = GameState::seeded(&GameConfig::standard(), 1);
+        gs.verbose = false;
+        gs
+    }
+
+    /// Sets up the puzzle file after `change` has edited its JSON.
+    fn set_up(config: &GameConfig, change: impl FnOnce(&mut Value)) -> Result<GameState, String> {
+        let mut value: Value = serde_json::from_str(&fs::read_to_string(PUZZLE).unwrap()).unwrap();
+        change(&mut value);
+        setup(&serde_json::from_value(value).unwrap(), config)
+    }
+
+    #[test]
+    fn puzzle_file_sets_up_the_roles_and_events() {
+        let gs = load(PUZZLE, &GameConfig::standard()).unwrap();
+        assert_eq!((gs.year_number, gs.infra_damage), (4, 1));
+        let joul = &gs.roles[&RoleName::from("Joul")];
+        assert_eq!(
+            (joul.facilities, joul.facilites_damaged, joul.resources),
+            (2, 1, 1)
+        );
+        assert_eq!(gs.roles[&RoleName::from("Man")].hand.len(), 1);
+        assert_eq!(gs.event_cards_in_play.len(), 4);
+
+        let err = load("configs/puzzles/missing.json", &GameConfig::standard()).unwrap_err();
+        assert!(err.starts_with("configs/puzzles/missing.json: "), "{}", err);
+    }
+
+    #[test]
+    fn puzzles_that_do_not_fit_the_config_are_rejected() {
+        let standard = GameConfig::standard();
+        type Change = fn(&mut Value);
+        let cases: Vec<(&str, Change)> = vec![
+            ("role Hab is missing", |v| {
+                v["roles"].as_object_mut().unwrap().remove("Hab");
+            }),
+            ("role Bio is not in the config", |v| {
+                v["roles"]["Bio"] = v["roles"]["Hab"].clone();
+            }),
+            ("role Hab has 0 facilities but its cap is", |v| {
+                v["roles"]["Hab"]["facilities"] = json!(0);
+            }),
+            ("role Hab has 99 facilities but its cap is", |v| {
+                v["roles"]["Hab"]["facilities"] = json!(99);
+            }),
+            ("more damaged facilities than facilities", |v| {
+                v["roles"]["Hab"]["damaged"] = json!(3);
+            }),
+            ("event Some(\"Raiding\") has no damage card", |v| {
+                v["events"][1]["Problem"][3] = Value::Null;
+            }),
+        ];
+        for (expected, change) in cases {
+            let err = set_up(&standard, change).err().unwrap();
+            assert!(
+                err.contains(expected),
+                "{:?} does not mention {:?}",
+                err,
+                expected
+            );
+        }
+
+        let abilities = GameConfig::load("configs/abilities.json").unwrap();
+        let err = set_up(&abilities, |_| {}).err().unwrap();
+        assert!(err.contains("rerolls damage"), "{}", err);
+        let escalation = GameConfig::load("configs/escalation.json").unwrap();
+        let err = set_up(&escalation, |_| {}).err().unwrap();
+        assert!(err.contains("extra damage cards"), "{}", err);
+    }
+
+    #[test]
+    fn orders_differ_in_at_least_one_position() {
+        let card = |name: &str| {
+            EventCard::Problem(
+                name.to_string(),
+                Vec::new(),
+                None,
+                Some(DamageCard::InfrastructureDamage),
+            )
+        };
+        assert_eq!(orders(&[]), vec![Vec::<EventCard>::new()]);
+        let distinct = orders(&[card("A"), card("B"), card("C")]);
+        assert_eq!(distinct.len(), 6);
+        assert_eq!(distinct.iter().collect::<HashSet<_>>().len(), 6);
+        let repeated = orders(&[card("A"), card("A"), card("B")]);
+        assert_eq!(
+            repeated,
+            vec![
+                vec![card("A"), card("A"), card("B")],
+                vec![card("A"), card("B"), card("A")],
+                vec![card("B"), card("A"), card("A")],
+            ]
+        );
+    }
+
+    #[test]
+    fn solver_pairs_doubled_damage_with_the_right_event() {
+        let mut gs = game();
+        let (hab, man) = (RoleName::from("Hab"), RoleName::from("Man"));
+        gs.roles.get_mut(&hab).unwrap().resources = 1;
+        gs.event_cards_in_play = vec![
+            EventCard::Problem(
+                "Quake".to_string(),
+                vec![(man.clone(), man.clone())],
+                Some(DamageRule::Double),
+                Some(DamageCard::FacilityDamage(hab.clone())),
+            ),
+            EventCard::Problem(
+                "Surge".to_string(),
+                vec![(RoleName::from("Joul"), RoleName::from("Joul"))],
+                Some(DamageRule::Infrastructure),
+                Some(DamageCard::InfrastructureDamage),
+            ),
+            EventCard::Problem(
+                "Mutiny".to_string(),
+                vec![(hab.clone(), hab.clone())],
+                None,
+                Some(DamageCard::FacilityDamage(man.clone())),
+            ),
+        ];
+        let solution = solve(&gs);
+        assert_eq!(solution.orders, 6);
+        for play in &solution.plays {
+            assert_eq!(
+                play.avoided(),
+                vec![&DamageCard::FacilityDamage(man.clone())]
+            );
+        }
+    }
+
+    #[test]
+    fn solver_tries_holding_cancel_cards() {
+        let mut gs = game();
+        let man = RoleName::from("Man");
+        gs.roles.get_mut(&man).unwrap().resources = 1;
+        gs.roles.get_mut(&man).unwrap().hand =
+            vec![BonusCard::Cancel("Quake".to_string(), vec![man.clone()])];
+        gs.event_cards_in_play = vec![EventCard::Problem(
+            "Quake".to_string(),
+            vec![(man.clone(), man.clone())],
+            None,
+            Some(DamageCard::InfrastructureDamage),
+        )];
+        let solution = solve(&gs);
+        let held: Vec<&Play> = solution
+            .plays
+            .iter()
+            .filter(|play| play.held == vec!["Quake".to_string()])
+            .collect();
+        assert!(!held.is_empty());
+        assert!(held
+            .iter()
+            .all(|play| play.log == vec![LogEntry::Resolved(0, "Quake".to_string())]));
+        assert!(solution
+            .plays
+            .iter()
+            .any(|play| matches!(play.log[0], LogEntry::Cancelled(..))));
+    }
+}
@@ -0,0 +1,148 @@
+use crate::config::GameConfig;
+use crate::series::YearSnapshot;
+use crate::strategy::Strategy;
+use crate::{GameState, LogEntry};
+use std::fmt;
+
+/// One seed played twice, with different strategies. The cards come out in
+/// the same order in both games, so any difference is down to the choices.
+pub struct Replay {
+    pub seed: u64,
+    pub games: [(String, GameState); 2],
+}
+
+impl Replay {
+    fn entries(gs: &GameState, year: usize) -> Vec<&LogEntry> {
+        gs.event_log.iter().filter(|e| e.year() == year).collect()
+    }
+
+    /// The first year, counted from 0, whose log or end-of-year colony
+    /// differs between the two games.
+    pub fn first_divergence(&self) -> Option<usize> {
+        let [(_, a), (_, b)] = &self.games;
+        (0..a.year_number.max(b.year_number)).find(|&year| {
+            Replay::entries(a, year) != Replay::entries(b, year)
+                || snapshot(a, year) != snapshot(b, year)
+        })
+    }
+}
+
+/// The colony at the end of the year counted from 0 as `year`.
+fn snapshot(gs: &GameState, year: usize) -> Option<&YearSnapshot> {
+    gs.history.iter().find(|s| s.year_number == year + 1)
+}
+
+fn outcome(gs: &GameState) -> String {
+    let names: Vec<_> = gs.roles.keys().collect();
+    let facilities: Vec<String> = names
+        .iter()
+        .map(|name| format!("{:?} {}", name, gs.roles[*name].facilities))
+        .collect();
+    let end = if gs.collapsed() {
+        format!("collapsed in year {}", gs.year_number)
+    } else {
+        format!("survived {} years", gs.year_number)
+    };
+    format!("{}, facilities {}", end, facilities.join(", "))
+}
+
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let [(a_name, a), (b_name, b)] = &self.games;
+        let width = a_name.len().max(b_name.len()) + 1;
+        writeln!(f, "Seed {}: {} against {}", self.seed, a_name, b_name)?;
+        let year = match self.first_divergence() {
+            Some(year) => year,
+            None => return writeln!(f, "The games never diverge\nOutcome: {}", outcome(a)),
+        };
+        writeln!(f, "Games first diverge in year {}", year + 1)?;
+        // Both games draw the same cards, so only what was done differs.
+        for (name, gs) in &self.games {
+            let done: Vec<&LogEntry> = Replay::entries(gs, year)
+                .into_iter()
+                .filter(|e| !matches!(e, LogEntry::Drawn(..)))
+                .collect();
+            writeln!(f, "  {:<w$} {:?}", format!("{}:", name), done, w = width)?;
+        }
+        writeln!(f, "End of year {}", year + 1)?;
+        for (name, gs) in &self.games {
+            match snapshot(gs, year) {
+                Some(s) => {
+                    let roles: Vec<String> = s
+                        .roles
+                        .iter()
+                        .map(|(role, r)| {
+                            format!("{:?} {} ({} damaged)", role, r.facilities, r.damaged)
+                        })
+                        .collect();
+                    writeln!(
+                        f,
+                        "  {:<w$} {}, infrastructure damage {}",
+                        format!("{}:", name),
+                        roles.join(", "),
+                        s.infra_damage,
+                        w = width
+                    )?
+                }
+                None => writeln!(f, "  {:<w$} already over", format!("{}:", name), w = width)?,
+            }
+        }
+        writeln!(f, "Outcome")?;
+        writeln!(
+            f,
+            "  {:<w$} {}",
+            format!("{}:", a_name),
+            outcome(a),
+            w = width
+        )?;
+        writeln!(
+            f,
+            "  {:<w$} {}",
+            format!("{}:", b_name),
+            outcome(b),
+            w = width
+        )
+    }
+}
+
+/// Plays the game dealt by `seed` once with each strategy.
+pub fn replay(config: &GameConfig, seed: u64, strategies: [&mut dyn Strategy; 2]) -> Replay {
+    let games = strategies.map(|strategy| {
+        let mut gs = GameState::seeded(config, seed);
+        gs.verbose = false;
+        strategy.reseed(seed);
+        gs.play_game(strategy);
+        (strategy.name().to_string(), gs)
+    });
+    Replay { seed, games }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::{self, Steady};
+
+    #[test]
+    fn strategies_that_choose_differently_report_where_the_games_diverge() {
+        let config = GameConfig::standard();
+        let same = replay(&config, 77, [&mut Steady, &mut Steady]);
+        assert_eq!(same.first_divergence(), None);
+        assert!(same.to_string().contains("The games never diverge"));
+
+        let mut build = strategy::by_name("build").unwrap();
+        let (played, year) = (0..20)
+            .find_map(|seed| {
+                let played = replay(&config, seed, [&mut Steady, build.as_mut()]);
+                played.first_divergence().map(|year| (played, year))
+            })
+            .unwrap();
+        let [(_, a), (_, b)] = &played.games;
+        for before in 0..year {
+            assert_eq!(Replay::entries(a, before), Replay::entries(b, before));
+            assert_eq!(snapshot(a, before), snapshot(b, before));
+        }
+        let text = played.to_string();
+        assert!(text.starts_with(&format!("Seed {}: steady against build\n", played.seed)));
+        assert!(text.contains(&format!("Games first diverge in year {}\n", year + 1)));
+    }
+}
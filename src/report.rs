@@ -0,0 +1,300 @@
+use crate::batch::BatchReport;
+use crate::{EventCardID, RoleName, INFRA_DAMAGE_LIMIT, YEARS_PER_GAME};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+const WIDTH: f64 = 560.0;
+const HEIGHT: f64 = 240.0;
+const MARGIN: f64 = 40.0;
+
+/// A bar or point on a chart: its label on the x axis and its value.
+type Series = Vec<(String, f64)>;
+
+/// Share of games still running at the end of each year, starting from 1
+/// before the first year.
+fn survival(report: &BatchReport) -> Series {
+    let games = report.results.len().max(1) as f64;
+    (0..=YEARS_PER_GAME)
+        .map(|year| {
+            let alive = report
+                .results
+                .iter()
+                .filter(|r| match r.history.iter().find(|s| s.year_number == year) {
+                    Some(s) => s.infra_damage < INFRA_DAMAGE_LIMIT,
+                    // Before a scenario's first year, or after a collapse.
+                    None => r.history.first().is_some_and(|s| year < s.year_number),
+                })
+                .count();
+            (year.to_string(), alive as f64 / games)
+        })
+        .collect()
+}
+
+/// Share of games ending with each number of facilities, per role.
+fn final_facilities(report: &BatchReport) -> BTreeMap<RoleName, Series> {
+    let mut counts: BTreeMap<RoleName, BTreeMap<usize, usize>> = BTreeMap::new();
+    for s in report.results.iter().filter_map(|r| r.history.last()) {
+        for (name, role) in &s.roles {
+            *counts
+                .entry(name.clone())
+                .or_default()
+                .entry(role.facilities)
+                .or_default() += 1;
+        }
+    }
+    let games = report.results.len().max(1) as f64;
+    counts
+        .into_iter()
+        .map(|(name, counts)| {
+            let top = counts.keys().copied().max().unwrap_or(0);
+            let series = (1..=top)
+                .map(|n| {
+                    let count = counts.get(&n).copied().unwrap_or(0);
+                    (n.to_string(), count as f64 / games)
+                })
+                .collect();
+            (name, series)
+        })
+        .collect()
+}
+
+/// Share of games ending with each amount of infrastructure damage.
+fn infra_damage(report: &BatchReport) -> Series {
+    let mut counts = vec![0; INFRA_DAMAGE_LIMIT + 1];
+    for s in report.results.iter().filter_map(|r| r.history.last()) {
+        counts[s.infra_damage.min(INFRA_DAMAGE_LIMIT)] += 1;
+    }
+    let games = report.results.len().max(1) as f64;
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(damage, count)| (damage.to_string(), count as f64 / games))
+        .collect()
+}
+
+/// How often each problem card fails when it is faced.
+fn failure_rates(report: &BatchReport) -> Series {
+    let mut faced: BTreeMap<&EventCardID, (usize, usize)> = BTreeMap::new();
+    for (id, failed) in report.results.iter().flat_map(|r| &r.card_outcomes) {
+        let (times, failures) = faced.entry(id).or_default();
+        *times += 1;
+        if *failed {
+            *failures += 1;
+        }
+    }
+    faced
+        .into_iter()
+        .map(|(id, (times, failures))| (id.clone(), failures as f64 / times as f64))
+        .collect()
+}
+
+/// Escapes text for use in HTML and SVG.
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Axes with gridlines for shares from 0 to 1.
+fn axes(svg: &mut String) {
+    let bottom = HEIGHT - MARGIN;
+    for tick in 0..=4 {
+        let share = tick as f64 / 4.0;
+        let y = bottom - share * (HEIGHT - 2.0 * MARGIN);
+        write!(
+            svg,
+            r##"<line x1="{m}" y1="{y:.1}" x2="{r}" y2="{y:.1}" stroke="#ddd"/><text x="{t}" y="{y:.1}" font-size="10" text-anchor="end" dy="3">{p:.0}%</text>"##,
+            m = MARGIN,
+            r = WIDTH - MARGIN / 2.0,
+            t = MARGIN - 4.0,
+            p = share * 100.0,
+        )
+        .unwrap();
+    }
+    write!(
+        svg,
+        r##"<line x1="{m}" y1="{b}" x2="{r}" y2="{b}" stroke="#333"/>"##,
+        m = MARGIN,
+        b = bottom,
+        r = WIDTH - MARGIN / 2.0,
+    )
+    .unwrap();
+}
+
+/// Left edge and width of each of `n` equal slots along the x axis.
+fn slots(n: usize) -> impl Iterator<Item = (f64, f64)> {
+    let width = (WIDTH - 1.5 * MARGIN) / n.max(1) as f64;
+    (0..n).map(move |i| (MARGIN + i as f64 * width, width))
+}
+
+fn label(svg: &mut String, x: f64, text: &str) {
+    write!(
+        svg,
+        r##"<text x="{x:.1}" y="{y}" font-size="10" text-anchor="middle">{t}</text>"##,
+        y = HEIGHT - MARGIN + 14.0,
+        t = escape(text),
+    )
+    .unwrap();
+}
+
+fn bar_chart(series: &Series) -> String {
+    let mut svg =
+        format!(r##"<svg width="{WIDTH}" height="{HEIGHT}" xmlns="http://www.w3.org/2000/svg">"##);
+    axes(&mut svg);
+    let bottom = HEIGHT - MARGIN;
+    for ((text, share), (left, width)) in series.iter().zip(slots(series.len())) {
+        let height = share * (HEIGHT - 2.0 * MARGIN);
+        write!(
+            svg,
+            r##"<rect x="{x:.1}" y="{y:.1}" width="{w:.1}" height="{height:.1}" fill="#4a7ab0"><title>{t}: {p:.1}%</title></rect>"##,
+            x = left + width * 0.15,
+            y = bottom - height,
+            w = width * 0.7,
+            t = escape(text),
+            p = share * 100.0,
+        )
+        .unwrap();
+        label(&mut svg, left + width / 2.0, text);
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+fn line_chart(series: &Series) -> String {
+    let mut svg =
+        format!(r##"<svg width="{WIDTH}" height="{HEIGHT}" xmlns="http://www.w3.org/2000/svg">"##);
+    axes(&mut svg);
+    let bottom = HEIGHT - MARGIN;
+    let points: Vec<(f64, f64)> = series
+        .iter()
+        .zip(slots(series.len()))
+        .map(|((_, share), (left, width))| {
+            (left + width / 2.0, bottom - share * (HEIGHT - 2.0 * MARGIN))
+        })
+        .collect();
+    let path: Vec<String> = points
+        .iter()
+        .map(|(x, y)| format!("{:.1},{:.1}", x, y))
+        .collect();
+    write!(
+        svg,
+        r##"<polyline points="{}" fill="none" stroke="#4a7ab0" stroke-width="2"/>"##,
+        path.join(" ")
+    )
+    .unwrap();
+    for ((text, share), (x, y)) in series.iter().zip(&points) {
+        write!(
+            svg,
+            r##"<circle cx="{x:.1}" cy="{y:.1}" r="3" fill="#4a7ab0"><title>year {t}: {p:.1}%</title></circle>"##,
+            t = escape(text),
+            p = share * 100.0,
+        )
+        .unwrap();
+        label(&mut svg, *x, text);
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+/// A single HTML page with the batch's charts drawn inline, so it can be
+/// opened or passed around without the simulator or a network connection.
+pub fn to_html(report: &BatchReport, config_name: &str) -> String {
+    let mut html = String::new();
+    let title = format!(
+        "Balance report: {}, strategy {}, {} games",
+        config_name,
+        report.strategy,
+        report.results.len()
+    );
+    write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n\
+         <style>body {{ font-family: sans-serif; margin: 2em; }} h2 {{ margin-top: 1.5em; }}</style>\n\
+         </head>\n<body>\n<h1>{0}</h1>\n<pre>{1}</pre>\n",
+        escape(&title),
+        escape(&report.to_string()),
+    )
+    .unwrap();
+    writeln!(
+        html,
+        "<h2>Survival by year</h2>\n<p>Share of games not yet collapsed at the end of each year.</p>\n{}",
+        line_chart(&survival(report))
+    )
+    .unwrap();
+    writeln!(html, "<h2>Final facilities</h2>").unwrap();
+    for (name, series) in final_facilities(report) {
+        writeln!(html, "<h3>{}</h3>\n{}", escape(&name.0), bar_chart(&series)).unwrap();
+    }
+    writeln!(
+        html,
+        "<h2>Final infrastructure damage</h2>\n<p>The colony collapses at {}.</p>\n{}",
+        INFRA_DAMAGE_LIMIT,
+        bar_chart(&infra_damage(report))
+    )
+    .unwrap();
+    writeln!(
+        html,
+        "<h2>Failure rate per card</h2>\n<p>Share of the times a problem card was faced that it was neither cancelled nor resolved.</p>\n{}",
+        bar_chart(&failure_rates(report))
+    )
+    .unwrap();
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::run_batch;
+    use crate::config::GameConfig;
+    use crate::strategy::Steady;
+
+    #[test]
+    fn report_has_a_chart_per_section_and_role() {
+        let report = run_batch(&GameConfig::standard(), &mut Steady, 20);
+        let html = to_html(&report, "configs/<odd> & \"new\".json");
+        assert!(html.starts_with("<!DOCTYPE html>\n<html>\n"));
+        assert!(html.ends_with("</body>\n</html>\n"));
+        assert!(html.contains(
+            "<title>Balance report: configs/&lt;odd&gt; &amp; &quot;new&quot;.json, \
+             strategy steady, 20 games</title>"
+        ));
+        assert!(!html.contains("<odd>"));
+        assert_eq!(html.matches("<h2>").count(), 4);
+        for role in &["Hab", "Joul", "Man"] {
+            assert!(html.contains(&format!("<h3>{}</h3>", role)));
+        }
+        assert_eq!(html.matches("<svg ").count(), 6);
+        assert_eq!(html.matches("</svg>").count(), 6);
+    }
+
+    #[test]
+    fn chart_series_are_shares_of_the_games() {
+        let report = run_batch(&GameConfig::standard(), &mut Steady, 20);
+        let alive = survival(&report);
+        assert_eq!(alive.len(), YEARS_PER_GAME + 1);
+        assert_eq!(alive[0], ("0".to_string(), 1.0));
+        assert!(alive.windows(2).all(|pair| pair[1].1 <= pair[0].1));
+
+        let damage: f64 = infra_damage(&report).iter().map(|(_, share)| share).sum();
+        assert!((damage - 1.0).abs() < 1e-9);
+        for series in final_facilities(&report).values() {
+            let total: f64 = series.iter().map(|(_, share)| share).sum();
+            assert!((total - 1.0).abs() < 1e-9);
+        }
+        assert!(failure_rates(&report)
+            .iter()
+            .all(|(_, rate)| (0.0..=1.0).contains(rate)));
+    }
+
+    #[test]
+    fn an_empty_batch_still_renders() {
+        let report = run_batch(&GameConfig::standard(), &mut Steady, 0);
+        let html = to_html(&report, "standard game");
+        assert!(html.ends_with("</html>\n"));
+        assert!(survival(&report).iter().all(|(_, share)| *share == 0.0));
+        assert!(failure_rates(&report).is_empty());
+        assert_eq!(html.matches("<h3>").count(), 0);
+    }
+}
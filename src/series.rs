@@ -0,0 +1,235 @@
+use crate::batch::GameResult;
+use crate::{BonusCard, GameState, LogEntry, RoleName};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::fs;
+
+/// A role's colony at the end of a year.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct RoleSnapshot {
+    pub facilities: usize,
+    pub damaged: usize,
+    /// Resources left over after the build phase.
+    pub resources: usize,
+    /// Bonus cards the role holds.
+    #[serde(default)]
+    pub hand: Vec<BonusCard>,
+}
+
+/// The colony at the end of a year, for charting how games develop.
+/// `year_number` counts the years played so far, so the first is 1.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize)]
+pub struct YearSnapshot {
+    pub year_number: usize,
+    pub roles: BTreeMap<RoleName, RoleSnapshot>,
+    pub infra_damage: usize,
+    pub events_drawn: usize,
+    pub events_cancelled: usize,
+    pub events_resolved: usize,
+    pub events_failed: usize,
+    pub bonus_cards_held: usize,
+}
+
+impl YearSnapshot {
+    /// Snapshot of `gs` just after the year before `gs.year_number` ended.
+    pub fn take(gs: &GameState) -> YearSnapshot {
+        let year = gs.year_number - 1;
+        let mut snapshot = YearSnapshot {
+            year_number: gs.year_number,
+            roles: gs
+                .roles
+                .iter()
+                .map(|(name, role)| {
+                    let role = RoleSnapshot {
+                        facilities: role.facilities,
+                        damaged: role.facilites_damaged,
+                        resources: role.resources,
+                        hand: role.hand.clone(),
+                    };
+                    (name.clone(), role)
+                })
+                .collect(),
+            infra_damage: gs.infra_damage,
+            events_drawn: 0,
+            events_cancelled: 0,
+            events_resolved: 0,
+            events_failed: 0,
+            bonus_cards_held: gs.bonus_cards_held(),
+        };
+        for entry in &gs.event_log {
+            match entry {
+                LogEntry::Drawn(y, _) if *y == year => snapshot.events_drawn += 1,
+                LogEntry::Cancelled(y, ..) if *y == year => snapshot.events_cancelled += 1,
+                LogEntry::Resolved(y, _) if *y == year => snapshot.events_resolved += 1,
+                LogEntry::Failed(y, ..) if *y == year => snapshot.events_failed += 1,
+                _ => {}
+            }
+        }
+        snapshot
+    }
+}
+
+/// One row per game and year, with three columns per role.
+pub fn to_csv(results: &[GameResult]) -> String {
+    let roles: BTreeSet<&RoleName> = results
+        .iter()
+        .flat_map(|r| &r.history)
+        .flat_map(|s| s.roles.keys())
+        .collect();
+    let mut out = String::from(
+        "game,year_number,infra_damage,events_drawn,events_cancelled,\
+         events_resolved,events_failed,bonus_cards_held",
+    );
+    for name in &roles {
+        write!(out, ",{0}_facilities,{0}_damaged,{0}_resources", name.0).unwrap();
+    }
+    out.push('\n');
+    for (game, result) in results.iter().enumerate() {
+        for s in &result.history {
+            write!(
+                out,
+                "{},{},{},{},{},{},{},{}",
+                game,
+                s.year_number,
+                s.infra_damage,
+                s.events_drawn,
+                s.events_cancelled,
+                s.events_resolved,
+                s.events_failed,
+                s.bonus_cards_held
+            )
+            .unwrap();
+            for name in &roles {
+                match s.roles.get(*name) {
+                    Some(r) => write!(out, ",{},{},{}", r.facilities, r.damaged, r.resources),
+                    None => write!(out, ",,,"),
+                }
+                .unwrap();
+            }
+            out.push('\n');
+        }
+    }
+    out
+}
+
+#[derive(Serialize)]
+struct GameSeries<'a> {
+    game: usize,
+    survived: bool,
+    years: &'a [YearSnapshot],
+}
+
+/// Every game's snapshots as a JSON array, one object per game.
+pub fn to_json(results: &[GameResult]) -> String {
+    let games: Vec<GameSeries> = results
+        .iter()
+        .enumerate()
+        .map(|(game, r)| GameSeries {
+            game,
+            survived: r.survived,
+            years: &r.history,
+        })
+        .collect();
+    serde_json::to_string_pretty(&games).unwrap()
+}
+
+/// Writes an export produced by `to_csv` or `to_json` to `path`.
+pub fn save(path: &str, text: &str) -> Result<(), String> {
+    fs::write(path, text).map_err(|e| format!("{}: {}", path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GameConfig;
+    use crate::strategy::Steady;
+
+    fn played(config: &GameConfig, seed: u64) -> GameResult {
+        let mut gs = GameState::seeded(config, seed);
+        gs.verbose = false;
+        gs.play_game(&mut Steady);
+        GameResult::from_game(&gs)
+    }
+
+    #[test]
+    fn snapshots_count_the_year_from_the_log() {
+        let mut gs = GameState::seeded(&GameConfig::standard(), 1);
+        gs.verbose = false;
+        gs.play_game(&mut Steady);
+        assert_eq!(gs.history.len(), gs.year_number);
+        for (year, s) in gs.history.iter().enumerate() {
+            let count = |matches: fn(&LogEntry) -> bool| {
+                gs.event_log
+                    .iter()
+                    .filter(|e| e.year() == year && matches(e))
+                    .count()
+            };
+            assert_eq!(s.year_number, year + 1);
+            assert_eq!(s.events_drawn, count(|e| matches!(e, LogEntry::Drawn(..))));
+            assert_eq!(
+                s.events_failed,
+                count(|e| matches!(e, LogEntry::Failed(..)))
+            );
+            assert_eq!(
+                s.events_resolved,
+                count(|e| matches!(e, LogEntry::Resolved(..)))
+            );
+        }
+        let last = gs.history.last().unwrap();
+        let facilities: Vec<usize> = last.roles.values().map(|r| r.facilities).collect();
+        let now: Vec<usize> = gs.roles.values().map(|r| r.facilities).collect();
+        assert_eq!(facilities, now);
+    }
+
+    #[test]
+    fn csv_has_a_row_per_game_year_and_blanks_for_missing_roles() {
+        let four = GameConfig::load("configs/four_roles.json").unwrap();
+        let results = [played(&GameConfig::standard(), 1), played(&four, 2)];
+        let csv = to_csv(&results);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "game,year_number,infra_damage,events_drawn,events_cancelled,\
+             events_resolved,events_failed,bonus_cards_held,\
+             Bio_facilities,Bio_damaged,Bio_resources,\
+             Hab_facilities,Hab_damaged,Hab_resources,\
+             Joul_facilities,Joul_damaged,Joul_resources,\
+             Man_facilities,Man_damaged,Man_resources"
+        );
+        let years = results[0].history.len() + results[1].history.len();
+        assert_eq!(lines.len(), years + 1);
+        assert!(lines.iter().all(|line| line.split(',').count() == 20));
+        assert!(lines[1].starts_with("0,1,"));
+        assert!(lines[1].contains(",,,"));
+        assert!(lines[lines.len() - 1].starts_with("1,"));
+        assert!(!lines[lines.len() - 1].contains(",,"));
+    }
+
+    #[test]
+    fn json_has_an_object_per_game_with_its_years() {
+        let results = [
+            played(&GameConfig::standard(), 1),
+            played(&GameConfig::standard(), 2),
+        ];
+        let json: serde_json::Value = serde_json::from_str(&to_json(&results)).unwrap();
+        let games = json.as_array().unwrap();
+        assert_eq!(games.len(), 2);
+        for (i, (game, result)) in games.iter().zip(&results).enumerate() {
+            assert_eq!(game["game"], i);
+            assert_eq!(game["survived"], result.survived);
+            let years = game["years"].as_array().unwrap();
+            assert_eq!(years.len(), result.history.len());
+            assert_eq!(years[0], serde_json::to_value(&result.history[0]).unwrap());
+            assert_eq!(years[0]["year_number"], 1);
+        }
+        assert_eq!(to_json(&[]), "[]");
+    }
+
+    #[test]
+    fn saving_to_a_missing_directory_names_the_path() {
+        let path = "no/such/dir/series.csv";
+        let error = save(path, "game\n").unwrap_err();
+        assert!(error.starts_with(path));
+    }
+}
@@ -0,0 +1,390 @@
+use crate::observation::Observation;
+use crate::{BonusCard, EventCard, Opportunity, RoleName};
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use std::collections::{BTreeMap, HashMap};
+
+/// Something the colony can do with its resources outside the automatic
+/// resolution of events.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub enum Action {
+    Build(RoleName),
+    /// Bring one damaged facility of the role back into production.
+    RepairFacility(RoleName),
+    /// Remove one infrastructure damage.
+    Repair,
+    /// Planning phase only: the first role pays the trade rate to the second.
+    Trade(RoleName, RoleName),
+    /// Planning phase only: the first role hands a bonus card to the second.
+    Give(RoleName, RoleName, BonusCard),
+    Pass,
+}
+
+/// Makes the decisions the rules leave to the players, seeing only what
+/// they would see.
+pub trait Strategy {
+    fn name(&self) -> &str;
+
+    /// Picks the next action. `options` is never empty and holds only actions
+    /// the colony can afford right now: trades and gifts of bonus cards in
+    /// the planning phase, builds and repairs in the build phase.
+    /// `Action::Pass` ends the phase.
+    fn choose_action(&mut self, obs: &Observation, options: &[Action]) -> Action;
+
+    /// Picks which card in `hand` the role discards when it holds more than
+    /// the hand limit, as a place in `hand`.
+    fn choose_discard(&mut self, obs: &Observation, role: &RoleName, hand: &[BonusCard]) -> usize {
+        least_useful(obs, role, hand)
+    }
+
+    /// Whether `role` plays its cancel card on `event` now rather than
+    /// holding it for a later one.
+    fn play_cancel(&mut self, _obs: &Observation, _role: &RoleName, _event: &EventCard) -> bool {
+        true
+    }
+
+    /// Whether `role`, which has not acted this year, builds with its build
+    /// card now rather than holding it for a later year.
+    fn play_build(&mut self, _obs: &Observation, _role: &RoleName) -> bool {
+        true
+    }
+
+    /// Restarts any random choices from `seed`, so that a game played again
+    /// with the same seed gets the same choices.
+    fn reseed(&mut self, _seed: u64) {}
+
+    /// A new player of the same kind that has seen nothing yet, to play one
+    /// role when table talk is limited. Its random choices are seeded from
+    /// this one's, so games still replay from a seed.
+    fn fresh(&mut self) -> Box<dyn Strategy>;
+}
+
+/// One player per role, each fresh from the same strategy, for when the
+/// roles cannot share what they know. Each choice goes to the player of the
+/// role it is seen by; choices seen by the whole table go to `open`.
+pub struct Seats<'a> {
+    open: &'a mut dyn Strategy,
+    seats: BTreeMap<RoleName, Box<dyn Strategy>>,
+}
+
+impl<'a> Seats<'a> {
+    pub fn new(open: &'a mut dyn Strategy, roles: &[RoleName]) -> Seats<'a> {
+        let seats = roles
+            .iter()
+            .map(|role| (role.clone(), open.fresh()))
+            .collect();
+        Seats { open, seats }
+    }
+
+    fn player(&mut self, obs: &Observation) -> &mut dyn Strategy {
+        let Seats { open, seats } = self;
+        match obs.viewer().and_then(move |role| seats.get_mut(role)) {
+            Some(seat) => seat.as_mut(),
+            None => &mut **open,
+        }
+    }
+}
+
+impl<'a> Strategy for Seats<'a> {
+    fn name(&self) -> &str {
+        self.open.name()
+    }
+
+    fn choose_action(&mut self, obs: &Observation, options: &[Action]) -> Action {
+        self.player(obs).choose_action(obs, options)
+    }
+
+    fn choose_discard(&mut self, obs: &Observation, role: &RoleName, hand: &[BonusCard]) -> usize {
+        self.player(obs).choose_discard(obs, role, hand)
+    }
+
+    fn play_cancel(&mut self, obs: &Observation, role: &RoleName, event: &EventCard) -> bool {
+        self.player(obs).play_cancel(obs, role, event)
+    }
+
+    fn play_build(&mut self, obs: &Observation, role: &RoleName) -> bool {
+        self.player(obs).play_build(obs, role)
+    }
+
+    fn fresh(&mut self) -> Box<dyn Strategy> {
+        self.open.fresh()
+    }
+}
+
+pub const NAMES: [&str; 6] = ["build", "repair", "trade", "steady", "thrifty", "count"];
+
+pub fn by_name(name: &str) -> Option<Box<dyn Strategy>> {
+    match name {
+        "build" => Some(Box::new(BuildRandom(ChaCha20Rng::from_entropy()))),
+        "repair" => Some(Box::new(RepairFirst(ChaCha20Rng::from_entropy()))),
+        "trade" => Some(Box::new(TradeToCover(ChaCha20Rng::from_entropy()))),
+        "steady" => Some(Box::new(Steady)),
+        "thrifty" => Some(Box::new(Thrifty)),
+        "count" => Some(Box::new(Counting)),
+        _ => None,
+    }
+}
+
+/// Never repairs; builds for a random role that has not acted yet.
+pub struct BuildRandom(ChaCha20Rng);
+
+impl Strategy for BuildRandom {
+    fn fresh(&mut self) -> Box<dyn Strategy> {
+        Box::new(BuildRandom(ChaCha20Rng::seed_from_u64(self.0.gen())))
+    }
+
+    fn name(&self) -> &str {
+        "build"
+    }
+
+    fn reseed(&mut self, seed: u64) {
+        self.0 = ChaCha20Rng::seed_from_u64(seed);
+    }
+
+    fn choose_action(&mut self, _obs: &Observation, options: &[Action]) -> Action {
+        gift(options).unwrap_or_else(|| random_build(options, &mut self.0))
+    }
+}
+
+/// Repairs infrastructure, then damaged facilities, whenever it can afford
+/// to, otherwise builds like `BuildRandom`.
+pub struct RepairFirst(ChaCha20Rng);
+
+impl Strategy for RepairFirst {
+    fn fresh(&mut self) -> Box<dyn Strategy> {
+        Box::new(RepairFirst(ChaCha20Rng::seed_from_u64(self.0.gen())))
+    }
+
+    fn name(&self) -> &str {
+        "repair"
+    }
+
+    fn reseed(&mut self, seed: u64) {
+        self.0 = ChaCha20Rng::seed_from_u64(seed);
+    }
+
+    fn choose_action(&mut self, _obs: &Observation, options: &[Action]) -> Action {
+        if let Some(gift) = gift(options) {
+            return gift;
+        }
+        if options.contains(&Action::Repair) {
+            return Action::Repair;
+        }
+        let facility_repair = options
+            .iter()
+            .find(|action| matches!(action, Action::RepairFacility(_)));
+        match facility_repair {
+            Some(action) => action.clone(),
+            None => random_build(options, &mut self.0),
+        }
+    }
+}
+
+fn random_build(options: &[Action], rng: &mut ChaCha20Rng) -> Action {
+    let builds: Vec<&Action> = options
+        .iter()
+        .filter(|action| matches!(action, Action::Build(_)))
+        .collect();
+    match builds.choose(rng) {
+        Some(action) => (*action).clone(),
+        None => Action::Pass,
+    }
+}
+
+/// Trades resources to the roles that are short of paying for this year's
+/// events, from roles with resources to spare, then builds like `BuildRandom`.
+pub struct TradeToCover(ChaCha20Rng);
+
+impl Strategy for TradeToCover {
+    fn fresh(&mut self) -> Box<dyn Strategy> {
+        Box::new(TradeToCover(ChaCha20Rng::seed_from_u64(self.0.gen())))
+    }
+
+    fn name(&self) -> &str {
+        "trade"
+    }
+
+    fn reseed(&mut self, seed: u64) {
+        self.0 = ChaCha20Rng::seed_from_u64(seed);
+    }
+
+    fn choose_action(&mut self, obs: &Observation, options: &[Action]) -> Action {
+        gift(options)
+            .or_else(|| covering_trade(obs, options))
+            .unwrap_or_else(|| random_build(options, &mut self.0))
+    }
+}
+
+/// Never picks at random, so a game's outcome depends only on the cards,
+/// which the exact analysis needs. Repairs first, trades like `TradeToCover`
+/// and builds for the role with the fewest facilities.
+pub struct Steady;
+
+impl Strategy for Steady {
+    fn fresh(&mut self) -> Box<dyn Strategy> {
+        Box::new(Steady)
+    }
+
+    fn name(&self) -> &str {
+        "steady"
+    }
+
+    fn choose_action(&mut self, obs: &Observation, options: &[Action]) -> Action {
+        if let Some(gift) = gift(options) {
+            return gift;
+        }
+        if options.contains(&Action::Repair) {
+            return Action::Repair;
+        }
+        if let Some(action) = options
+            .iter()
+            .find(|action| matches!(action, Action::RepairFacility(_)))
+        {
+            return action.clone();
+        }
+        if let Some(trade) = covering_trade(obs, options) {
+            return trade;
+        }
+        options
+            .iter()
+            .filter_map(|action| match action {
+                Action::Build(role) => Some((obs.role(role).facilities, role)),
+                _ => None,
+            })
+            .min()
+            .map_or(Action::Pass, |(_, role)| Action::Build(role.clone()))
+    }
+}
+
+/// Plays like `Steady` but holds bonus cards while they would be wasted:
+/// cancel cards for events the roles can pay for themselves, build cards
+/// while the role is at its facility cap.
+pub struct Thrifty;
+
+impl Strategy for Thrifty {
+    fn fresh(&mut self) -> Box<dyn Strategy> {
+        Box::new(Thrifty)
+    }
+
+    fn name(&self) -> &str {
+        "thrifty"
+    }
+
+    fn choose_action(&mut self, obs: &Observation, options: &[Action]) -> Action {
+        Steady.choose_action(obs, options)
+    }
+
+    fn play_cancel(&mut self, obs: &Observation, _role: &RoleName, event: &EventCard) -> bool {
+        !obs.can_pay(event)
+    }
+
+    fn play_build(&mut self, obs: &Observation, role: &RoleName) -> bool {
+        let role = obs.role(role);
+        role.facilities < role.facility_cap
+    }
+}
+
+/// Plays like `Steady` but counts cards: builds for the role most likely to
+/// be asked to pay for the next event, going by what is left in the deck.
+pub struct Counting;
+
+impl Strategy for Counting {
+    fn fresh(&mut self) -> Box<dyn Strategy> {
+        Box::new(Counting)
+    }
+
+    fn name(&self) -> &str {
+        "count"
+    }
+
+    fn choose_action(&mut self, obs: &Observation, options: &[Action]) -> Action {
+        let builds: Vec<&RoleName> = options
+            .iter()
+            .filter_map(|action| match action {
+                Action::Build(role) => Some(role),
+                _ => None,
+            })
+            .collect();
+        match Steady.choose_action(obs, options) {
+            Action::Build(_) => {
+                let chance = |role: &RoleName| obs.chance_next_event_needs(role);
+                let mut best = builds[0];
+                for role in builds {
+                    if chance(role) > chance(best) {
+                        best = role;
+                    }
+                }
+                Action::Build(best.clone())
+            }
+            other => other,
+        }
+    }
+}
+
+/// The first gift of a bonus card among `options`. A card only ever goes to
+/// a role that can play it, so every strategy hands cards over.
+fn gift(options: &[Action]) -> Option<Action> {
+    options
+        .iter()
+        .find(|action| matches!(action, Action::Give(..)))
+        .cloned()
+}
+
+/// A card the role cannot play, failing that a build card for a role
+/// already at its facility cap, failing that the oldest card.
+pub fn least_useful(obs: &Observation, role: &RoleName, hand: &[BonusCard]) -> usize {
+    let role = obs.role(role);
+    let at_cap = role.facilities >= role.facility_cap;
+    hand.iter()
+        .position(|card| !role.can_play(card))
+        .or_else(|| {
+            hand.iter()
+                .position(|card| at_cap && matches!(card, BonusCard::Build(_)))
+        })
+        .unwrap_or(0)
+}
+
+/// The first trade among `options` that moves spare resources to a role
+/// that cannot otherwise pay for this year's events.
+fn covering_trade(obs: &Observation, options: &[Action]) -> Option<Action> {
+    let rate = obs.rules().trade_rate?;
+    let balance = resource_balance(obs);
+    options
+        .iter()
+        .find(|action| match action {
+            Action::Trade(from, to) => {
+                let spare = balance.get(from).copied().unwrap_or(0);
+                let short = balance.get(to).copied().unwrap_or(0);
+                spare >= rate.give as isize && short < 0
+            }
+            _ => false,
+        })
+        .cloned()
+}
+
+/// Each role's resources minus what it owes for events in play that no
+/// cancel card will take care of.
+fn resource_balance(obs: &Observation) -> HashMap<RoleName, isize> {
+    let mut balance: HashMap<RoleName, isize> = obs
+        .roles()
+        .into_iter()
+        .map(|role| (role.name.clone(), role.resources as isize))
+        .collect();
+    for card in obs.events_in_play() {
+        match card.id() {
+            Some(id) if card.is_problem() && !obs.will_be_cancelled(id) => {
+                for (_, cost) in card.role_costs() {
+                    *balance.entry(cost.clone()).or_insert(0) -= 1;
+                }
+            }
+            // Opportunities pay out before any event has to be paid for.
+            _ => {
+                if let EventCard::Opportunity(_, Opportunity::Resources(role, amount)) = &card {
+                    *balance.entry(role.clone()).or_insert(0) += *amount as isize;
+                }
+            }
+        }
+    }
+    balance
+}